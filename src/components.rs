@@ -1,10 +1,62 @@
-use bevy::{prelude::*};
+use std::time::Duration;
 
-use crate::{plugins::timers::WithTimer};
+use bevy::{prelude::*, utils::{HashMap, HashSet}};
+use serde::{Deserialize, Serialize};
+
+use crate::plugins::{ai::EnemyArchetype, timers::{Cooldown, WithTimer}};
 
 #[derive(Component)]
 pub struct HealthBar;
 
+#[derive(Component)]
+pub struct LoadingUi;
+
+#[derive(Component)]
+pub struct MainMenuUi;
+
+#[derive(Component)]
+pub struct PauseUi;
+
+#[derive(Component)]
+pub struct GameOverUi;
+
+#[derive(Component)]
+pub struct Hud;
+
+#[derive(Component)]
+pub struct HudSkillSlot(pub usize);
+
+#[derive(Component)]
+pub struct HudSkillIcon(pub usize);
+
+/// Darkens the part of a skill slot whose cooldown is still running.
+#[derive(Component)]
+pub struct HudCooldown(pub usize);
+
+#[derive(Component)]
+pub struct HudHealthFill;
+
+#[derive(Component)]
+pub struct HudHealthText;
+
+#[derive(Component)]
+pub struct HudWave;
+
+#[derive(Component)]
+pub struct HudScore;
+
+#[derive(Component)]
+pub struct HudCoins;
+
+#[derive(Component)]
+pub struct HudPotions;
+
+#[derive(Component)]
+pub struct HudLevel;
+
+#[derive(Component)]
+pub struct LevelUpUi;
+
 #[derive(Component)]
 pub struct RandomWalkAi(pub Timer);
 
@@ -14,22 +66,198 @@ impl RandomWalkAi {
     }
 }
 
-#[derive(Component, Clone)]
-pub enum EquippedSkill {
-    FireBall,
-    Punch(f32),
-    Slash,
+impl Default for RandomWalkAi {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AiState {
+    Wander,
+    Chase,
+    Attack,
+    Flee,
+    Return,
+}
+
+/// Tuning of an enemy's behaviour, distances are in world units.
+#[derive(Clone, Debug)]
+pub struct BehaviourParams {
+    pub detect_radius: f32,
+    pub attack_range: f32,
+    /// Health ratio at or below which the enemy runs away from the player.
+    pub flee_health: f32,
+}
+
+#[derive(Component)]
+pub struct AiBehaviour {
+    pub state: AiState,
+    pub params: BehaviourParams,
+}
+
+impl AiBehaviour {
+    pub fn new(params: BehaviourParams) -> Self {
+        Self { state: AiState::Wander, params }
+    }
+}
+
+/// Point an enemy wanders around, it stops chasing once it gets further than `leash_radius` away from it.
+#[derive(Component)]
+pub struct HomePosition {
+    pub position: Vec2,
+    pub wander_radius: f32,
+    pub leash_radius: f32,
+}
+
+/// Number of slots selectable with the number keys and cast with the left mouse button.
+pub const HOTBAR_SLOTS: usize = 4;
+/// Slot cast with the right mouse button.
+pub const SECONDARY_SLOT: usize = HOTBAR_SLOTS;
+
+/// Ids of skills in the loaded `SkillBook` a character can cast, `active` is the hotbar slot in use.
+#[derive(Component, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct SkillLoadout {
+    pub slots: [Option<String>; HOTBAR_SLOTS + 1],
+    pub active: usize,
+}
+
+impl SkillLoadout {
+    pub fn new(hotbar: &[&str]) -> Self {
+        let mut slots: [Option<String>; HOTBAR_SLOTS + 1] = Default::default();
+        for (slot, skill) in slots.iter_mut().zip(hotbar.iter().take(HOTBAR_SLOTS)) {
+            *slot = Some(skill.to_string());
+        }
+        Self { slots, active: 0 }
+    }
+
+    pub fn with_secondary(mut self, skill: &str) -> Self {
+        self.slots[SECONDARY_SLOT] = Some(skill.to_string());
+        self
+    }
+
+    /// Unlocks the skill in the first free hotbar slot, when the skill is already known or the hotbar
+    /// is full it replaces the secondary skill instead. Returns the slot the skill ended up in.
+    pub fn learn(&mut self, skill: &str) -> usize {
+        let hotbar = &mut self.slots[..HOTBAR_SLOTS];
+        if !hotbar.iter().any(|slot| slot.as_deref() == Some(skill)) {
+            if let Some((index, slot)) = hotbar.iter_mut().enumerate().find(|(_, slot)| slot.is_none()) {
+                *slot = Some(skill.to_string());
+                return index;
+            }
+        }
+        self.slots[SECONDARY_SLOT] = Some(skill.to_string());
+        SECONDARY_SLOT
+    }
+}
+
+/// Running cooldowns keyed by skill id, a skill without an entry is ready.
+#[derive(Component, Default)]
+pub struct Cooldowns(pub HashMap<String, Timer>);
+
+impl Cooldowns {
+    pub fn start(&mut self, skill: &str, seconds: f32) {
+        self.0.insert(skill.to_string(), Timer::from_seconds(seconds, TimerMode::Once));
+    }
+
+    pub fn is_ready(&self, skill: &str) -> bool {
+        !self.0.contains_key(skill)
+    }
+
+    /// Fraction of the cooldown still left, from 1.0 right after the cast down to 0.0 when ready.
+    pub fn remaining(&self, skill: &str) -> f32 {
+        self.0.get(skill).map_or(0.0, |timer| timer.percent_left())
+    }
+}
+
+impl Cooldown for Cooldowns {
+    fn tick(&mut self, dt: Duration) -> bool {
+        self.0.retain(|_, timer| !timer.tick(dt).finished());
+        false
+    }
 }
 
+/// Side the next melee attack is thrown from, flipped after every use.
+#[derive(Component)]
+pub struct MeleeSide(pub f32);
+
 #[derive(Component)]
 pub struct PlayerControlled;
 
 #[derive(Component)]
 pub struct Character;
 
+#[derive(Component, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Faction {
+    Player,
+    Enemies,
+    Neutral,
+}
+
 #[derive(Component)]
 pub struct TargetPosition(pub Vec2);
 
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Deserialize)]
+pub enum AnimationState {
+    Idle,
+    Walk,
+    Attack,
+    Hit,
+    Death,
+}
+
+impl AnimationState {
+    pub fn is_looping(&self) -> bool {
+        matches!(self, AnimationState::Idle | AnimationState::Walk)
+    }
+}
+
+/// Plays the clips of `sprite` from the animation book, one-shot clips go back to `base` once they end.
+#[derive(Component)]
+pub struct Animation {
+    pub sprite: String,
+    pub state: AnimationState,
+    pub base: AnimationState,
+    pub frame: usize,
+    pub elapsed: f32,
+}
+
+impl Animation {
+    pub fn new(sprite: impl Into<String>) -> Self {
+        Self {
+            sprite: sprite.into(),
+            state: AnimationState::Idle,
+            base: AnimationState::Idle,
+            frame: 0,
+            elapsed: 0.0,
+        }
+    }
+
+    /// Switches the looping clip, a one-shot clip that is playing finishes first.
+    pub fn set_base(&mut self, state: AnimationState) {
+        if self.base == state {
+            return;
+        }
+        self.base = state;
+        if self.state.is_looping() {
+            self.restart(state);
+        }
+    }
+
+    /// Plays a one-shot clip from its first frame, nothing interrupts death.
+    pub fn play(&mut self, state: AnimationState) {
+        if self.state != AnimationState::Death {
+            self.restart(state);
+        }
+    }
+
+    fn restart(&mut self, state: AnimationState) {
+        self.state = state;
+        self.frame = 0;
+        self.elapsed = 0.0;
+    }
+}
+
 #[derive(Component)]
 pub struct WiggleEffect(pub f32);
 
@@ -47,34 +275,91 @@ impl RotateAroundPoint {
 
 #[derive(Component)]
 pub struct Attack {
-    pub value: f32
+    pub value: f32,
+    pub kind: DamageKind,
+    /// Distance the target is pushed away, in pixels.
+    pub knockback: f32,
+    pub effects: Vec<StatusEffect>,
 }
 
+/// Pushes a character that was hit, through its `KinematicCharacterController`.
 #[derive(Component)]
-pub struct Health {
-    pub max: f32,
-    pub act: f32,
+pub struct Knockback {
+    /// Pixels per second.
+    pub velocity: Vec2,
+    pub timer: Timer,
 }
 
-#[derive(Component)]
-pub struct Projectile;
+impl Knockback {
+    pub fn new(velocity: Vec2, seconds: f32) -> Self {
+        Self {
+            velocity,
+            timer: Timer::from_seconds(seconds, TimerMode::Once),
+        }
+    }
+}
+
+impl WithTimer for Knockback {
+    fn timer(&self) -> &Timer {
+        &self.timer
+    }
 
+    fn timer_mut(&mut self) -> &mut Timer {
+        &mut self.timer
+    }
+}
+
+/// Character can neither move nor attack.
 #[derive(Component)]
-pub struct Slash;
+pub struct Stunned(pub Timer);
+
+impl Stunned {
+    pub fn new(seconds: f32) -> Self {
+        Self(Timer::from_seconds(seconds, TimerMode::Once))
+    }
+}
+
+impl WithTimer for Stunned {
+    fn timer(&self) -> &Timer {
+        &self.0
+    }
+
+    fn timer_mut(&mut self) -> &mut Timer {
+        &mut self.0
+    }
+}
 
+/// Hits landing on the character are ignored.
 #[derive(Component)]
-pub struct Wall;
+pub struct Invulnerable(pub Timer);
+
+impl Invulnerable {
+    pub fn new(seconds: f32) -> Self {
+        Self(Timer::from_seconds(seconds, TimerMode::Once))
+    }
+}
+
+impl WithTimer for Invulnerable {
+    fn timer(&self) -> &Timer {
+        &self.0
+    }
+
+    fn timer_mut(&mut self) -> &mut Timer {
+        &mut self.0
+    }
+}
 
+/// Sprite blinks to show the character was hit.
 #[derive(Component)]
-pub struct AttackCD(pub Timer);
+pub struct HitFlash(pub Timer);
 
-impl AttackCD {
+impl HitFlash {
     pub fn new(seconds: f32) -> Self {
         Self(Timer::from_seconds(seconds, TimerMode::Once))
     }
 }
 
-impl WithTimer for AttackCD {
+impl WithTimer for HitFlash {
     fn timer(&self) -> &Timer {
         &self.0
     }
@@ -84,6 +369,350 @@ impl WithTimer for AttackCD {
     }
 }
 
+/// Lingering effect an attack leaves on the characters it hits.
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub enum StatusEffect {
+    /// Fire damage every `interval` seconds, a new burn refreshes the old one.
+    Burn { damage: f32, interval: f32, duration: f32 },
+    /// Damage every `interval` seconds for each stack, every application adds a stack.
+    Poison { damage: f32, interval: f32, duration: f32 },
+    /// Movement speed is multiplied by `factor`, the strongest slow wins.
+    Slow { factor: f32, duration: f32 },
+    /// Neither moving nor attacking, a shorter stun never cuts a longer one short.
+    Stun { duration: f32 },
+}
+
+/// Damage dealt over time on behalf of `source`.
+pub struct DamageOverTime {
+    pub source: Entity,
+    pub damage: f32,
+    pub tick: Timer,
+    pub duration: Timer,
+}
+
+impl DamageOverTime {
+    pub fn new(source: Entity, damage: f32, interval: f32, duration: f32) -> Self {
+        Self {
+            source,
+            damage,
+            tick: Timer::from_seconds(interval, TimerMode::Repeating),
+            duration: Timer::from_seconds(duration, TimerMode::Once),
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Burn(pub DamageOverTime);
+
+impl WithTimer for Burn {
+    fn timer(&self) -> &Timer {
+        &self.0.duration
+    }
+
+    fn timer_mut(&mut self) -> &mut Timer {
+        &mut self.0.duration
+    }
+}
+
+#[derive(Component)]
+pub struct Poison {
+    pub dot: DamageOverTime,
+    pub stacks: u32,
+}
+
+impl WithTimer for Poison {
+    fn timer(&self) -> &Timer {
+        &self.dot.duration
+    }
+
+    fn timer_mut(&mut self) -> &mut Timer {
+        &mut self.dot.duration
+    }
+}
+
+#[derive(Component)]
+pub struct Slow {
+    pub factor: f32,
+    pub timer: Timer,
+}
+
+impl Slow {
+    pub fn new(factor: f32, seconds: f32) -> Self {
+        Self {
+            factor,
+            timer: Timer::from_seconds(seconds, TimerMode::Once),
+        }
+    }
+}
+
+impl WithTimer for Slow {
+    fn timer(&self) -> &Timer {
+        &self.timer
+    }
+
+    fn timer_mut(&mut self) -> &mut Timer {
+        &mut self.timer
+    }
+}
+
+#[derive(Component, Clone, Copy)]
+pub struct AttackOwner {
+    pub entity: Entity,
+    pub faction: Faction,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default, Deserialize)]
+pub enum DamageKind {
+    #[default]
+    Physical,
+    Fire,
+    Poison,
+}
+
+/// Flat reduction of every physical hit.
+#[derive(Component)]
+pub struct Armor(pub f32);
+
+/// Share of damage of each kind that is ignored, negative values make the character weak to it.
+#[derive(Component, Default)]
+pub struct Resistances(pub HashMap<DamageKind, f32>);
+
+/// Chance of an attack to deal its damage multiplied.
+#[derive(Component)]
+pub struct CriticalHit {
+    pub chance: f32,
+    pub multiplier: f32,
+}
+
+/// Multipliers of damage dealt and taken, status effects change them.
+#[derive(Component)]
+pub struct DamageModifiers {
+    pub dealt: f32,
+    pub taken: f32,
+}
+
+impl Default for DamageModifiers {
+    fn default() -> Self {
+        Self { dealt: 1.0, taken: 1.0 }
+    }
+}
+
+#[derive(Component)]
+pub struct Health {
+    pub max: f32,
+    pub act: f32,
+}
+
+/// Character attributes raised by levelling up.
+#[derive(Component, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Stats {
+    /// Multiplier of the damage dealt.
+    pub strength: f32,
+    /// Multiplier of the movement speed.
+    pub speed: f32,
+    /// `Health.max` follows it.
+    pub max_health: f32,
+    /// Share of every skill cooldown that is skipped.
+    pub cooldown_reduction: f32,
+}
+
+impl Stats {
+    pub fn new(max_health: f32) -> Self {
+        Self {
+            strength: 1.0,
+            speed: 1.0,
+            max_health,
+            cooldown_reduction: 0.0,
+        }
+    }
+}
+
+/// Experience gathered toward the next level, `unspent` level-ups still wait for an upgrade to be picked.
+#[derive(Component, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Experience {
+    pub level: u32,
+    pub current: u32,
+    pub unspent: u32,
+}
+
+impl Default for Experience {
+    fn default() -> Self {
+        Self { level: 1, current: 0, unspent: 0 }
+    }
+}
+
+/// Plays the death animation with colliders and AI off, the entity is despawned once its `TTL` runs out.
+#[derive(Component)]
+pub struct Dying;
+
+/// Something the player can carry.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Item {
+    Coins(u32),
+    HealthPotion { heal: f32 },
+    SkillScroll { skill: String },
+}
+
+impl Item {
+    /// Name of the item's sprite in the sprite sheet.
+    pub fn sprite_name(&self) -> &'static str {
+        match self {
+            Item::Coins(_) => "coin",
+            Item::HealthPotion { .. } => "potion",
+            Item::SkillScroll { .. } => "scroll",
+        }
+    }
+}
+
+/// Item dropped with the given chance.
+#[derive(Clone, Debug)]
+pub struct LootDrop {
+    pub item: Item,
+    pub chance: f32,
+}
+
+/// Items the character may drop when it dies.
+#[derive(Component, Default)]
+pub struct LootTable(pub Vec<LootDrop>);
+
+/// Item lying on the ground, picked up by the player on contact.
+#[derive(Component)]
+pub struct Loot(pub Item);
+
+/// Coins and consumables the player carries.
+#[derive(Component, Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Inventory {
+    pub coins: u32,
+    pub items: Vec<Item>,
+}
+
+impl Inventory {
+    pub fn potions(&self) -> usize {
+        self.items.iter().filter(|item| matches!(item, Item::HealthPotion { .. })).count()
+    }
+
+    /// Removes the first carried potion and returns how much it heals.
+    pub fn take_potion(&mut self) -> Option<f32> {
+        let index = self.items.iter().position(|item| matches!(item, Item::HealthPotion { .. }))?;
+        match self.items.remove(index) {
+            Item::HealthPotion { heal } => Some(heal),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Projectile;
+
+/// Skill the attack was spawned from.
+#[derive(Component)]
+pub struct FromSkill(pub String);
+
+/// Targets the attack already hit, it never hits them again.
+#[derive(Component, Default)]
+pub struct HitTargets(pub HashSet<Entity>);
+
+/// Steers the projectile toward the nearest hostile character.
+#[derive(Component)]
+pub struct Homing {
+    /// Radians per second.
+    pub turn_rate: f32,
+}
+
+/// Number of targets the projectile passes through before it is spent.
+#[derive(Component)]
+pub struct Piercing(pub u32);
+
+/// Number of times the projectile bounces off walls before it is spent.
+#[derive(Component)]
+pub struct Bouncing(pub u32);
+
+/// Explodes when the attack is spent on impact or its `TTL` runs out.
+#[derive(Component, Deserialize, Clone, Debug)]
+pub struct Explosive {
+    pub damage: f32,
+    /// Relative to `SPRITE_DRAW_SIZE`.
+    pub radius: f32,
+    /// Share of the damage lost at the edge of the radius.
+    #[serde(default)]
+    pub falloff: f32,
+    /// Distance targets are pushed away from the center, in pixels.
+    #[serde(default)]
+    pub knockback: f32,
+    #[serde(default)]
+    pub effects: Vec<StatusEffect>,
+}
+
+/// Short-lived area that hits every hostile character inside `radius` once.
+#[derive(Component)]
+pub struct Explosion {
+    pub owner: AttackOwner,
+    pub damage: f32,
+    pub kind: DamageKind,
+    /// In pixels.
+    pub radius: f32,
+    pub falloff: f32,
+    pub knockback: f32,
+    pub effects: Vec<StatusEffect>,
+}
+
+/// Breaks the projectile into `count` fragments when it is spent on impact.
+#[derive(Component)]
+pub struct Splitting {
+    pub count: u32,
+    /// Angle between the outermost fragments and the flight direction, in radians.
+    pub spread: f32,
+}
+
+#[derive(Component)]
+pub struct Slash;
+
+#[derive(Component)]
+pub struct Wall;
+
+/// Cosmetic square that drifts, shrinks and fades out over its `TTL`.
+#[derive(Component)]
+pub struct Particle {
+    /// Pixels per second.
+    pub velocity: Vec2,
+    /// Share of the velocity lost every second.
+    pub drag: f32,
+    /// Size at spawn and at the end of the lifetime.
+    pub size: (f32, f32),
+    pub color: Color,
+    pub end_color: Color,
+}
+
+/// Keeps emitting particles of the named emitter while the entity lives.
+#[derive(Component)]
+pub struct ParticleTrail {
+    pub emitter: String,
+    /// Particles owed from previous frames.
+    pub pending: f32,
+}
+
+impl ParticleTrail {
+    pub fn new(emitter: impl Into<String>) -> Self {
+        Self { emitter: emitter.into(), pending: 0.0 }
+    }
+}
+
+/// Text that rises and fades out over its `TTL`.
+#[derive(Component)]
+pub struct FloatingText {
+    /// Pixels per second.
+    pub rise_speed: f32,
+}
+
+#[derive(Component)]
+pub struct Floor;
+
+/// Place in the level where enemies of the given archetype appear.
+#[derive(Component)]
+pub struct EnemySpawnPoint {
+    pub archetype: EnemyArchetype,
+}
+
 #[derive(Component)]
 pub struct TTL(pub Timer);
 
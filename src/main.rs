@@ -1,6 +1,4 @@
-use std::f32::consts::FRAC_PI_2;
-
-use bevy::{prelude::*, window::WindowResolution, math::{vec2}};
+use bevy::{prelude::*, window::WindowResolution, math::{vec2}, asset::LoadState};
 
 #[cfg(debug_assertions)]
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
@@ -13,12 +11,12 @@ use bevy_rapier2d::{
         RigidBody,
         RapierConfiguration,
         KinematicCharacterController,
-        ActiveCollisionTypes, LockedAxes, ActiveEvents,
+        LockedAxes, ActiveEvents,
     },
     render::RapierDebugRenderPlugin
 };
-use components::{Wall, PlayerControlled, EquippedSkill, Character, Health, HealthBar, RandomWalkAi};
-use plugins::{timers::TimersPlugin, collision::CollisionPlugin, events::EventsPlugin, skills::SkillsPlugin, character::CharacterPlugin, ai::AiPlugin};
+use components::{Animation, Armor, CriticalHit, DamageModifiers, PlayerControlled, SkillLoadout, Cooldowns, Character, Health, HealthBar, RandomWalkAi, Faction, AiBehaviour, HomePosition, Inventory, Stats, Experience};
+use plugins::{timers::TimersPlugin, collision::CollisionPlugin, events::EventsPlugin, skills::{SkillsPlugin, SkillBook}, character::CharacterPlugin, animation::{AnimationPlugin, SpriteSheet}, spawner::{SpawnerPlugin, WaveBook}, damage::DamagePlugin, floating_text::FloatingTextPlugin, score::ScorePlugin, status::StatusPlugin, explosion::ExplosionPlugin, particles::{ParticlesPlugin, ParticleBook}, loot::LootPlugin, inventory::InventoryPlugin, progression::ProgressionPlugin, save::SavePlugin, ai::{AiPlugin, EnemyArchetype, DEFAULT_WANDER_RADIUS, DEFAULT_LEASH_RADIUS}, game_over::GameOverPlugin, menu::MenuPlugin, loading::LoadingPlugin, hud::HudPlugin, rng::{RngPlugin, seed_from_args}, level::{LevelPlugin, Level}};
 
 pub mod components;
 pub mod plugins;
@@ -30,14 +28,20 @@ const SCALE_FACTOR: f32 = 3.0;
 const SPRITE_DRAW_SIZE: f32 = SPRITE_SIZE * SCALE_FACTOR;
 const CHARACTER_Z_INDEX: f32 = 1.0;
 const ATTACK_Z_INDEX: f32 = 1.5;
-const FIREBALL_SPEED: f32 = 500.0;
-const PUNCH_SPEED: f32 = 500.0;
-const SLASH_SPEED: f32 = FRAC_PI_2;
+const PLAYER_CRITICAL_CHANCE: f32 = 0.1;
+const PLAYER_CRITICAL_MULTIPLIER: f32 = 2.0;
+const PLAYER_MAX_HEALTH: f32 = 5.0;
+const LEVELS: [&str; 2] = ["levels/arena.level.ron", "levels/pillars.level.ron"];
 
 #[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, States)]
 pub enum GameState {
     #[default]
+    Loading,
+    MainMenu,
     Playing,
+    Paused,
+    LevelUp,
+    GameOver,
 }
 
 fn main() {
@@ -50,21 +54,43 @@ fn main() {
                 ..default()
             }),
             ..default()
-        }).set(ImagePlugin::default_nearest()))
+        }).set(ImagePlugin::default_nearest()).set(AssetPlugin {
+            watch_for_changes: true,
+            ..default()
+        }))
         .add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(10.0))
         .add_state::<GameState>()
         .insert_resource(RapierConfiguration {
             gravity: Vec2::ZERO,
             ..Default::default()
         })
+        .add_plugin(RngPlugin { seed: seed_from_args() })
         .add_plugin(EventsPlugin)
         .add_plugin(TimersPlugin)
         .add_plugin(CollisionPlugin)
         .add_plugin(SkillsPlugin)
         .add_plugin(CharacterPlugin)
         .add_plugin(AiPlugin)
+        .add_plugin(LoadingPlugin)
+        .add_plugin(MenuPlugin)
+        .add_plugin(GameOverPlugin)
+        .add_plugin(HudPlugin)
+        .add_plugin(LevelPlugin)
+        .add_plugin(AnimationPlugin)
+        .add_plugin(SpawnerPlugin)
+        .add_plugin(DamagePlugin)
+        .add_plugin(FloatingTextPlugin)
+        .add_plugin(ScorePlugin)
+        .add_plugin(StatusPlugin)
+        .add_plugin(ExplosionPlugin)
+        .add_plugin(ParticlesPlugin)
+        .add_plugin(LootPlugin)
+        .add_plugin(InventoryPlugin)
+        .add_plugin(ProgressionPlugin)
+        .add_plugin(SavePlugin)
         .add_startup_system(setup)
-        .add_system(setup_world.in_schedule(OnEnter(GameState::Playing)))
+        .add_system(resume_physics.in_schedule(OnEnter(GameState::Playing)))
+        .add_system(freeze_physics.in_schedule(OnExit(GameState::Playing)))
         ;
 
     #[cfg(debug_assertions)]
@@ -81,6 +107,25 @@ fn main() {
 pub struct GameResources {
     image_handle: Handle<Image>,
     font_handle: Handle<Font>,
+    atlas_handle: Handle<TextureAtlas>,
+    skills_handle: Handle<SkillBook>,
+    sheet_handle: Handle<SpriteSheet>,
+    waves_handle: Handle<WaveBook>,
+    particles_handle: Handle<ParticleBook>,
+    levels: Vec<Handle<Level>>,
+}
+
+impl GameResources {
+    pub fn load_state(&self, asset_server: &AssetServer) -> LoadState {
+        asset_server.get_group_load_state([
+            self.image_handle.id(),
+            self.font_handle.id(),
+            self.skills_handle.id(),
+            self.sheet_handle.id(),
+            self.waves_handle.id(),
+            self.particles_handle.id(),
+        ].into_iter().chain(self.levels.iter().map(|level| level.id())))
+    }
 }
 
 fn setup(
@@ -88,95 +133,149 @@ fn setup(
     asset_server: Res<AssetServer>,
 ) {
     let image_handle = asset_server.load("sprites.png");
+    let sheet_handle = asset_server.load("sprites.aseprite.json");
+    let atlas_handle = asset_server.load("sprites.aseprite.json#atlas");
     let font_handle = asset_server.load("QuinqueFive.ttf");
+    let skills_handle = asset_server.load("default.skills.ron");
+    let waves_handle = asset_server.load("default.waves.ron");
+    let particles_handle = asset_server.load("default.particles.ron");
+    let levels = LEVELS.iter().map(|path| asset_server.load(*path)).collect();
 
     commands.insert_resource(GameResources {
         image_handle,
         font_handle,
+        atlas_handle,
+        skills_handle,
+        sheet_handle,
+        waves_handle,
+        particles_handle,
+        levels,
     });
     commands.spawn(Camera2dBundle::default());
 }
 
 
-fn setup_world(
-    mut commands: Commands,
-    game_resources: Res<GameResources>,
-) {
-    commands.spawn((
-            RigidBody::Fixed,
-            Collider::cuboid(SPRITE_DRAW_SIZE / 2.0, SPRITE_DRAW_SIZE / 2.0),
-            SpriteBundle {
-                sprite: Sprite {
-                    custom_size: Some(vec2(SPRITE_DRAW_SIZE, SPRITE_DRAW_SIZE)),
-                    rect: Some(Rect::new(6.0 * SPRITE_SIZE, 0., 7.0 * SPRITE_SIZE, SPRITE_SIZE)),
-                    ..default()
-                },
-                texture: game_resources.image_handle.clone(),
-                transform: Transform::from_xyz(0., -100.0, CHARACTER_Z_INDEX),
-                ..default()
-            },
-            ActiveCollisionTypes::all(),
-            Wall,
-        ));
-
+pub fn spawn_player(
+    commands: &mut Commands,
+    game_resources: &GameResources,
+    position: Vec2,
+) -> Entity {
     commands.spawn((
         Character,
         PlayerControlled,
-        SpriteBundle {
-            sprite: Sprite {
+        Faction::Player,
+        Health {
+            act: PLAYER_MAX_HEALTH,
+            max: PLAYER_MAX_HEALTH
+        },
+        SpriteSheetBundle {
+            sprite: TextureAtlasSprite {
                 custom_size: Some(vec2(SPRITE_DRAW_SIZE, SPRITE_DRAW_SIZE)),
-                rect: Some(Rect::new(0.0 * SPRITE_SIZE, 0., 1.0 * SPRITE_SIZE, SPRITE_SIZE)),
                 ..default()
             },
-            texture: game_resources.image_handle.clone(),
-            transform: Transform::from_xyz(0., 0., CHARACTER_Z_INDEX),
+            texture_atlas: game_resources.atlas_handle.clone(),
+            transform: Transform::from_xyz(position.x, position.y, CHARACTER_Z_INDEX),
             ..default()
         },
-        EquippedSkill::Punch(SPRITE_DRAW_SIZE * 0.3),
+        Animation::new("player"),
+        CriticalHit {
+            chance: PLAYER_CRITICAL_CHANCE,
+            multiplier: PLAYER_CRITICAL_MULTIPLIER,
+        },
+        DamageModifiers::default(),
+        (
+            SkillLoadout::new(&["punch", "slash", "fireball", "shard"]).with_secondary("fireball"),
+            Cooldowns::default(),
+            Inventory::default(),
+            Stats::new(PLAYER_MAX_HEALTH),
+            Experience::default(),
+        ),
         RigidBody::KinematicVelocityBased,
         Collider::cuboid(SPRITE_DRAW_SIZE * 0.3, SPRITE_DRAW_SIZE * 0.4),
         KinematicCharacterController::default(),
         LockedAxes::ROTATION_LOCKED,
         ActiveEvents::COLLISION_EVENTS
-    ));
+    ))
+    .with_children(spawn_health_bar)
+    .id()
+}
 
+pub fn spawn_enemy(
+    commands: &mut Commands,
+    game_resources: &GameResources,
+    archetype: EnemyArchetype,
+    position: Vec2,
+) -> Entity {
     commands.spawn((
         Character,
+        Faction::Enemies,
         Health {
-            act: 3.0,
-            max: 3.0
+            act: archetype.max_health(),
+            max: archetype.max_health()
         },
         RandomWalkAi::new(),
-        SpriteBundle {
-            sprite: Sprite {
-                custom_size: Some(vec2(SPRITE_DRAW_SIZE, SPRITE_DRAW_SIZE)),
-                rect: Some(Rect::new(8.0 * SPRITE_SIZE, 0., 9.0 * SPRITE_SIZE, SPRITE_SIZE)),
+        AiBehaviour::new(archetype.behaviour()),
+        HomePosition {
+            position,
+            wander_radius: DEFAULT_WANDER_RADIUS,
+            leash_radius: DEFAULT_LEASH_RADIUS,
+        },
+        (
+            SpriteSheetBundle {
+                sprite: TextureAtlasSprite {
+                    custom_size: Some(vec2(SPRITE_DRAW_SIZE, SPRITE_DRAW_SIZE)),
+                    ..default()
+                },
+                texture_atlas: game_resources.atlas_handle.clone(),
+                transform: Transform::from_xyz(position.x, position.y, CHARACTER_Z_INDEX),
                 ..default()
             },
-            texture: game_resources.image_handle.clone(),
-            transform: Transform::from_xyz(200., 0., CHARACTER_Z_INDEX),
-            ..default()
-        },
-        EquippedSkill::Punch(SPRITE_DRAW_SIZE * 0.3),
+            Animation::new(archetype.sprite_name()),
+        ),
+        (
+            Armor(archetype.armor()),
+            archetype.resistances(),
+            DamageModifiers::default(),
+        ),
+        (
+            SkillLoadout::new(&[archetype.skill()]),
+            Cooldowns::default(),
+            archetype.loot(),
+        ),
         RigidBody::KinematicVelocityBased,
         Collider::cuboid(SPRITE_DRAW_SIZE * 0.4, SPRITE_DRAW_SIZE * 0.4),
         KinematicCharacterController::default(),
         LockedAxes::ROTATION_LOCKED,
         ActiveEvents::COLLISION_EVENTS
     ))
-    .with_children(|builder| {
-        builder.spawn((
-            SpriteBundle {
-                sprite: Sprite {
-                    anchor: bevy::sprite::Anchor::BottomLeft,
-                    color: Color::rgb(0.95, 0.25, 0.25),
-                    custom_size: Some(Vec2::new(SPRITE_DRAW_SIZE, 8.0)),
-                    ..default()
-                },
-                transform: Transform::from_translation(Vec3::new(-SPRITE_DRAW_SIZE * 0.5, SPRITE_DRAW_SIZE * 0.6, 1.0)),
+    .with_children(spawn_health_bar)
+    .id()
+}
+
+fn freeze_physics(
+    mut rapier_config: ResMut<RapierConfiguration>,
+) {
+    rapier_config.physics_pipeline_active = false;
+}
+
+fn resume_physics(
+    mut rapier_config: ResMut<RapierConfiguration>,
+) {
+    rapier_config.physics_pipeline_active = true;
+}
+
+fn spawn_health_bar(builder: &mut ChildBuilder) {
+    builder.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                anchor: bevy::sprite::Anchor::BottomLeft,
+                color: Color::rgb(0.95, 0.25, 0.25),
+                custom_size: Some(Vec2::new(SPRITE_DRAW_SIZE, 8.0)),
                 ..default()
             },
-            HealthBar
-        ));
-    });
+            transform: Transform::from_translation(Vec3::new(-SPRITE_DRAW_SIZE * 0.5, SPRITE_DRAW_SIZE * 0.6, 1.0)),
+            ..default()
+        },
+        HealthBar
+    ));
 }
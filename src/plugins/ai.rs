@@ -1,9 +1,28 @@
-use std::time::Duration;
+use std::{time::Duration, f32::consts::TAU};
 
-use bevy::{prelude::*, math::vec2};
+use bevy::{prelude::*, math::vec2, utils::HashMap};
 use rand::Rng;
+use serde::Deserialize;
 
-use crate::{components::{RandomWalkAi, TargetPosition}, GameState};
+use crate::{components::{RandomWalkAi, TargetPosition, AiBehaviour, AiState, BehaviourParams, PlayerControlled, Health, SkillLoadout, Cooldowns, HomePosition, Stunned, Resistances, DamageKind, Item, LootDrop, LootTable}, GameState};
+
+use super::{events::SkillEvent, skills::on_skill_used, rng::GameRng};
+
+/// Distance a fleeing enemy tries to put between itself and the player.
+const FLEE_DISTANCE: f32 = 150.0;
+/// Detection radius is scaled by this once an enemy noticed the player, so it does not give up right at the edge.
+const LOSE_INTEREST_FACTOR: f32 = 1.5;
+pub const DEFAULT_WANDER_RADIUS: f32 = 100.0;
+pub const DEFAULT_LEASH_RADIUS: f32 = 400.0;
+
+/// What an enemy weighs when picking its next state.
+type AiSenses = (&'static Transform, &'static mut AiBehaviour, Option<&'static Health>, Option<&'static HomePosition>);
+/// Enemies that may pick a new point to wander to.
+type Wanderers = (Entity, &'static Transform, &'static mut RandomWalkAi, Option<&'static mut TargetPosition>, Option<&'static AiBehaviour>, Option<&'static HomePosition>);
+/// Enemies moved by their current state.
+type AiMovers = (Entity, &'static Transform, &'static AiBehaviour, Option<&'static mut TargetPosition>, Option<&'static HomePosition>);
+/// Enemies that may use their active skill on the player.
+type AiAttackers = (Entity, &'static Transform, &'static AiBehaviour, &'static SkillLoadout, &'static Cooldowns);
 
 pub struct AiPlugin;
 
@@ -11,23 +30,179 @@ impl Plugin for AiPlugin {
     fn build(&self, app: &mut App) {
         app
         .add_systems((
-            update_ai,
+            update_ai_state,
+            update_ai.after(update_ai_state),
+            move_by_ai_state.after(update_ai_state),
+            attack_player.after(update_ai_state).before(on_skill_used),
         ).in_set(OnUpdate(GameState::Playing)));
     }
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+pub enum EnemyArchetype {
+    Brawler,
+    Skirmisher,
+    Berserker,
+}
+
+impl EnemyArchetype {
+    /// Name of the archetype's sprite in the sprite sheet.
+    pub fn sprite_name(&self) -> &'static str {
+        match self {
+            EnemyArchetype::Brawler => "brawler",
+            EnemyArchetype::Skirmisher => "skirmisher",
+            EnemyArchetype::Berserker => "berserker",
+        }
+    }
+
+    pub fn max_health(&self) -> f32 {
+        match self {
+            EnemyArchetype::Brawler => 3.0,
+            EnemyArchetype::Skirmisher => 2.0,
+            EnemyArchetype::Berserker => 4.0,
+        }
+    }
+
+    pub fn armor(&self) -> f32 {
+        match self {
+            EnemyArchetype::Brawler => 0.2,
+            EnemyArchetype::Skirmisher => 0.0,
+            EnemyArchetype::Berserker => 0.1,
+        }
+    }
+
+    /// Share of each damage kind the archetype shrugs off.
+    pub fn resistances(&self) -> Resistances {
+        match self {
+            EnemyArchetype::Brawler => Resistances::default(),
+            EnemyArchetype::Skirmisher => Resistances(HashMap::from_iter([(DamageKind::Fire, 0.5)])),
+            EnemyArchetype::Berserker => Resistances(HashMap::from_iter([(DamageKind::Fire, -0.25)])),
+        }
+    }
+
+    /// Scrolls drop the archetype's own skill.
+    pub fn loot(&self) -> LootTable {
+        let drops = match self {
+            EnemyArchetype::Brawler => vec![
+                LootDrop { item: Item::Coins(1), chance: 0.8 },
+                LootDrop { item: Item::HealthPotion { heal: 1.0 }, chance: 0.1 },
+            ],
+            EnemyArchetype::Skirmisher => vec![
+                LootDrop { item: Item::Coins(2), chance: 0.8 },
+                LootDrop { item: Item::HealthPotion { heal: 1.0 }, chance: 0.15 },
+                LootDrop { item: Item::SkillScroll { skill: self.skill().to_string() }, chance: 0.05 },
+            ],
+            EnemyArchetype::Berserker => vec![
+                LootDrop { item: Item::Coins(3), chance: 1.0 },
+                LootDrop { item: Item::HealthPotion { heal: 2.0 }, chance: 0.25 },
+                LootDrop { item: Item::SkillScroll { skill: self.skill().to_string() }, chance: 0.1 },
+            ],
+        };
+        LootTable(drops)
+    }
+
+    pub fn skill(&self) -> &'static str {
+        match self {
+            EnemyArchetype::Brawler => "punch",
+            EnemyArchetype::Skirmisher => "fireball",
+            EnemyArchetype::Berserker => "slash",
+        }
+    }
+
+    pub fn behaviour(&self) -> BehaviourParams {
+        match self {
+            EnemyArchetype::Brawler => BehaviourParams {
+                detect_radius: 250.0,
+                attack_range: 60.0,
+                flee_health: 0.25,
+            },
+            EnemyArchetype::Skirmisher => BehaviourParams {
+                detect_radius: 350.0,
+                attack_range: 220.0,
+                flee_health: 0.5,
+            },
+            EnemyArchetype::Berserker => BehaviourParams {
+                detect_radius: 300.0,
+                attack_range: 70.0,
+                flee_health: 0.0,
+            },
+        }
+    }
+}
+
+fn next_ai_state(state: AiState, params: &BehaviourParams, distance: Option<f32>, health_ratio: f32) -> AiState {
+    let Some(distance) = distance else {
+        return AiState::Wander;
+    };
+    let detect_radius = if state == AiState::Wander {
+        params.detect_radius
+    } else {
+        params.detect_radius * LOSE_INTEREST_FACTOR
+    };
+    if distance > detect_radius {
+        AiState::Wander
+    } else if health_ratio <= params.flee_health {
+        AiState::Flee
+    } else if distance <= params.attack_range {
+        AiState::Attack
+    } else {
+        AiState::Chase
+    }
+}
+
+/// Sends an enemy that chased too far back home and keeps it going until it gets there.
+fn leashed_ai_state(state: AiState, next: AiState, home: &HomePosition, home_distance: f32) -> AiState {
+    match state {
+        AiState::Return if home_distance > home.wander_radius => AiState::Return,
+        AiState::Chase | AiState::Attack if home_distance > home.leash_radius => AiState::Return,
+        _ => next,
+    }
+}
+
+/// Uniformly distributed point in the disc of `radius` around `home`.
+fn wander_point<R: Rng>(rng: &mut R, home: Vec2, radius: f32) -> Vec2 {
+    let angle = rng.gen_range(0.0..TAU);
+    let distance = radius * rng.gen_range(0.0_f32..1.0).sqrt();
+    home + vec2(angle.cos(), angle.sin()) * distance
+}
+
+fn update_ai_state(
+    player_q: Query<&Transform, With<PlayerControlled>>,
+    mut ai_q: Query<AiSenses, Without<PlayerControlled>>,
+) {
+    let player_position = player_q.get_single().ok().map(|transform| transform.translation.truncate());
+    for (transform, mut behaviour, health, home) in ai_q.iter_mut() {
+        let position = transform.translation.truncate();
+        let distance = player_position.map(|player_position| player_position.distance(position));
+        let health_ratio = health.map_or(1.0, |health| health.act / health.max);
+        let mut state = next_ai_state(behaviour.state, &behaviour.params, distance, health_ratio);
+        if let Some(home) = home {
+            state = leashed_ai_state(behaviour.state, state, home, home.position.distance(position));
+        }
+        if behaviour.state != state {
+            behaviour.state = state;
+        }
+    }
+}
+
 fn update_ai(
     mut commands: Commands,
     time: Res<Time>,
-    mut ai_q: Query<(Entity, &mut RandomWalkAi, Option<&mut TargetPosition>)>,
+    mut rng: ResMut<GameRng>,
+    mut ai_q: Query<Wanderers>,
 ) {
-    let mut rng = rand::thread_rng();
     let dt = time.delta();
-    for (entity, mut ai, target_positon) in ai_q.iter_mut()  {
+    for (entity, transform, mut ai, target_positon, behaviour, home) in ai_q.iter_mut()  {
+        if behaviour.is_some_and(|behaviour| behaviour.state != AiState::Wander) {
+            continue;
+        }
         if !ai.0.tick(dt).finished() {
             continue;
         }
-        let new_position = vec2(rng.gen_range(0_f32..100_f32), rng.gen_range(0_f32..100_f32));
+        let new_position = match home {
+            Some(home) => wander_point(&mut *rng, home.position, home.wander_radius),
+            None => wander_point(&mut *rng, transform.translation.truncate(), DEFAULT_WANDER_RADIUS),
+        };
         if let Some(mut tp) = target_positon {
             tp.0 = new_position;
         } else {
@@ -38,4 +213,139 @@ fn update_ai(
     };
 
 
-}
\ No newline at end of file
+}
+
+fn move_by_ai_state(
+    mut commands: Commands,
+    player_q: Query<&Transform, With<PlayerControlled>>,
+    mut ai_q: Query<AiMovers, Without<PlayerControlled>>,
+) {
+    let Ok(player_transform) = player_q.get_single() else {
+        return;
+    };
+    let player_position = player_transform.translation.truncate();
+    for (entity, transform, behaviour, target_position, home) in ai_q.iter_mut() {
+        let position = transform.translation.truncate();
+        let new_position = match (behaviour.state, home) {
+            (AiState::Wander, _) | (AiState::Return, None) => continue,
+            (AiState::Attack, _) => {
+                if target_position.is_some() {
+                    commands.entity(entity).remove::<TargetPosition>();
+                }
+                continue;
+            },
+            (AiState::Chase, _) => player_position,
+            (AiState::Flee, _) => position + (position - player_position).normalize_or_zero() * FLEE_DISTANCE,
+            (AiState::Return, Some(home)) => home.position,
+        };
+        if let Some(mut tp) = target_position {
+            tp.0 = new_position;
+        } else {
+            commands.entity(entity).insert(TargetPosition(new_position));
+        }
+    }
+}
+
+fn attack_player(
+    mut skill_events: EventWriter<SkillEvent>,
+    player_q: Query<&Transform, With<PlayerControlled>>,
+    ai_q: Query<AiAttackers, (Without<PlayerControlled>, Without<Stunned>)>,
+) {
+    let Ok(player_transform) = player_q.get_single() else {
+        return;
+    };
+    let player_position = player_transform.translation.truncate();
+    for (entity, transform, behaviour, loadout, cooldowns) in ai_q.iter() {
+        if behaviour.state != AiState::Attack {
+            continue;
+        }
+        let Some(skill) = loadout.slots[loadout.active].as_ref().filter(|skill| cooldowns.is_ready(skill)) else {
+            continue;
+        };
+        let position = transform.translation.truncate();
+        let spawn_vector = (player_position - position).normalize_or_zero();
+        skill_events.send(SkillEvent {
+            skill: skill.clone(),
+            parent: entity,
+            angle: spawn_vector.y.atan2(spawn_vector.x),
+            start_position: position,
+            spawn_vector_norm: spawn_vector,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{prelude::*, math::vec2};
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use crate::{
+        GameState,
+        components::{AiState, AiBehaviour, HomePosition, RandomWalkAi, TargetPosition},
+        plugins::{events::SkillEvent, rng::RngPlugin},
+    };
+
+    use super::{AiPlugin, EnemyArchetype, wander_point, leashed_ai_state};
+
+    fn home() -> HomePosition {
+        HomePosition {
+            position: vec2(500.0, -300.0),
+            wander_radius: 100.0,
+            leash_radius: 400.0,
+        }
+    }
+
+    #[test]
+    fn wander_points_stay_around_home() {
+        let home = home();
+        let mut rng = StdRng::seed_from_u64(42);
+        for _ in 0..1000 {
+            let point = wander_point(&mut rng, home.position, home.wander_radius);
+            assert!(point.distance(home.position) <= home.wander_radius);
+        }
+    }
+
+    #[test]
+    fn chasing_past_the_leash_returns_home() {
+        let home = home();
+        assert_eq!(leashed_ai_state(AiState::Chase, AiState::Chase, &home, 350.0), AiState::Chase);
+        assert_eq!(leashed_ai_state(AiState::Chase, AiState::Chase, &home, 450.0), AiState::Return);
+        assert_eq!(leashed_ai_state(AiState::Attack, AiState::Attack, &home, 450.0), AiState::Return);
+    }
+
+    #[test]
+    fn returning_enemy_ignores_player_until_home() {
+        let home = home();
+        assert_eq!(leashed_ai_state(AiState::Return, AiState::Chase, &home, 150.0), AiState::Return);
+        assert_eq!(leashed_ai_state(AiState::Return, AiState::Chase, &home, 50.0), AiState::Chase);
+    }
+
+    fn wander_targets(seed: u64) -> Vec<Vec2> {
+        let mut app = App::new();
+        app
+            .add_plugins(MinimalPlugins)
+            .add_state::<GameState>()
+            .add_event::<SkillEvent>()
+            .add_plugin(RngPlugin { seed: Some(seed) })
+            .add_plugin(AiPlugin);
+        app.world.insert_resource(State(GameState::Playing));
+        let enemies: Vec<Entity> = (0..5).map(|_| {
+            app.world.spawn((
+                Transform::default(),
+                RandomWalkAi::new(),
+                AiBehaviour::new(EnemyArchetype::Brawler.behaviour()),
+                home(),
+            )).id()
+        }).collect();
+
+        app.update();
+
+        enemies.iter().map(|enemy| app.world.get::<TargetPosition>(*enemy).unwrap().0).collect()
+    }
+
+    #[test]
+    fn same_seed_picks_same_wander_targets() {
+        assert_eq!(wander_targets(11), wander_targets(11));
+        assert_ne!(wander_targets(11), wander_targets(12));
+    }
+}
@@ -0,0 +1,113 @@
+use bevy::{
+    prelude::*,
+    reflect::TypeUuid,
+    utils::HashMap,
+};
+
+use crate::{GameState, GameResources, components::{Animation, AnimationState}};
+
+use super::aseprite::AsepriteLoader;
+
+pub struct AnimationPlugin;
+
+impl Plugin for AnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_asset::<SpriteSheet>()
+            .init_asset_loader::<AsepriteLoader>()
+            // runs after the update flush so freshly spawned sprites never show the wrong frame,
+            // and keeps going on the game over screen so the player's death plays out
+            .add_system(
+                animate_sprites
+                    .in_base_set(CoreSet::PostUpdate)
+                    .run_if(in_state(GameState::Playing).or_else(in_state(GameState::GameOver)))
+            );
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct AnimationFrame {
+    /// Index into the sprite sheet atlas.
+    pub index: usize,
+    /// How long the frame is shown, in seconds.
+    pub duration: f32,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct AnimationClip {
+    pub frames: Vec<AnimationFrame>,
+}
+
+/// Named sprites of the sheet and their animation clips, grouped by sprite name and then by state.
+#[derive(TypeUuid, Debug)]
+#[uuid = "c2a7e5d4-8f31-4b6c-a0d9-3e5f7b1c2d84"]
+pub struct SpriteSheet {
+    pub atlas: Handle<TextureAtlas>,
+    pub sprites: HashMap<String, usize>,
+    pub clips: HashMap<String, HashMap<AnimationState, AnimationClip>>,
+}
+
+impl SpriteSheet {
+    /// Clip for the given state, sprites that have no clip for it fall back to idle.
+    pub fn clip(&self, sprite: &str, state: AnimationState) -> Option<&AnimationClip> {
+        let clips = self.clips.get(sprite)?;
+        clips.get(&state).or_else(|| clips.get(&AnimationState::Idle))
+    }
+
+    /// Atlas index of the sprite's still frame.
+    pub fn sprite(&self, sprite: &str) -> Option<usize> {
+        self.sprites.get(sprite).copied()
+    }
+}
+
+fn animate_sprites(
+    time: Res<Time>,
+    game_resources: Res<GameResources>,
+    sprite_sheets: Res<Assets<SpriteSheet>>,
+    mut animation_q: Query<(&mut Animation, &mut TextureAtlasSprite)>,
+) {
+    let Some(sprite_sheet) = sprite_sheets.get(&game_resources.sheet_handle) else {
+        return;
+    };
+    let dt = time.delta_seconds();
+    for (mut animation, mut sprite) in animation_q.iter_mut() {
+        let Some(clip) = sprite_sheet.clip(&animation.sprite, animation.state).filter(|clip| !clip.frames.is_empty()) else {
+            if let Some(index) = sprite_sheet.sprite(&animation.sprite) {
+                if sprite.index != index {
+                    sprite.index = index;
+                }
+            }
+            continue;
+        };
+        animation.frame = animation.frame.min(clip.frames.len() - 1);
+        animation.elapsed += dt;
+        let mut frame = clip.frames[animation.frame];
+        while frame.duration > 0.0 && animation.elapsed >= frame.duration {
+            animation.elapsed -= frame.duration;
+            if animation.frame + 1 < clip.frames.len() {
+                animation.frame += 1;
+            } else if animation.state.is_looping() {
+                animation.frame = 0;
+            } else if animation.state == AnimationState::Death {
+                // death holds its last frame
+                animation.elapsed = 0.0;
+                break;
+            } else {
+                animation.state = animation.base;
+                animation.frame = 0;
+                break;
+            }
+            frame = clip.frames[animation.frame];
+        }
+
+        let Some(clip) = sprite_sheet.clip(&animation.sprite, animation.state) else {
+            continue;
+        };
+        let Some(frame) = clip.frames.get(animation.frame) else {
+            continue;
+        };
+        if sprite.index != frame.index {
+            sprite.index = frame.index;
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use std::path::Path;
+
+use bevy::{
+    prelude::*,
+    math::vec2,
+    asset::{AssetLoader, AssetPath, LoadContext, LoadedAsset},
+    utils::{BoxedFuture, HashMap},
+};
+use serde::Deserialize;
+
+use crate::components::AnimationState;
+
+use super::animation::{SpriteSheet, AnimationClip, AnimationFrame};
+
+/// Reads the JSON that Aseprite writes next to an exported sheet (`--format json-array`).
+/// Every slice becomes a named sprite and every tag named after an animation state (`idle`, `walk`, ...)
+/// becomes a clip of each sprite, showing the slice's bounds on the tagged frames.
+#[derive(Default)]
+pub struct AsepriteLoader;
+
+#[derive(Deserialize)]
+struct AsepriteRect {
+    x: f32,
+    y: f32,
+    w: f32,
+    h: f32,
+}
+
+#[derive(Deserialize)]
+struct AsepriteSize {
+    w: f32,
+    h: f32,
+}
+
+#[derive(Deserialize)]
+struct AsepriteFrame {
+    frame: AsepriteRect,
+    /// In milliseconds.
+    duration: u32,
+}
+
+#[derive(Deserialize)]
+struct AsepriteTag {
+    name: String,
+    from: usize,
+    to: usize,
+    #[serde(default)]
+    direction: String,
+}
+
+#[derive(Deserialize)]
+struct AsepriteSliceKey {
+    frame: usize,
+    bounds: AsepriteRect,
+}
+
+#[derive(Deserialize)]
+struct AsepriteSlice {
+    name: String,
+    keys: Vec<AsepriteSliceKey>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AsepriteMeta {
+    image: String,
+    size: AsepriteSize,
+    #[serde(default)]
+    frame_tags: Vec<AsepriteTag>,
+    #[serde(default)]
+    slices: Vec<AsepriteSlice>,
+}
+
+#[derive(Deserialize)]
+struct AsepriteSheet {
+    frames: Vec<AsepriteFrame>,
+    meta: AsepriteMeta,
+}
+
+fn animation_state(tag: &str) -> Option<AnimationState> {
+    match tag.to_lowercase().as_str() {
+        "idle" => Some(AnimationState::Idle),
+        "walk" => Some(AnimationState::Walk),
+        "attack" => Some(AnimationState::Attack),
+        "hit" => Some(AnimationState::Hit),
+        "death" => Some(AnimationState::Death),
+        _ => None,
+    }
+}
+
+/// Frame numbers of the tag in play order.
+fn tag_frames(tag: &AsepriteTag) -> Vec<usize> {
+    let forward: Vec<usize> = (tag.from..=tag.to).collect();
+    match tag.direction.as_str() {
+        "reverse" => forward.into_iter().rev().collect(),
+        "pingpong" => {
+            let back = forward.iter().rev().skip(1).take(forward.len().saturating_sub(2)).copied();
+            forward.iter().copied().chain(back).collect()
+        },
+        _ => forward,
+    }
+}
+
+/// Cuts every slice out of every frame into `atlas`, returning the named sprites and their clips.
+fn cut_sheet(
+    sheet: &AsepriteSheet,
+    atlas: &mut TextureAtlas,
+) -> (HashMap<String, usize>, HashMap<String, HashMap<AnimationState, AnimationClip>>) {
+    // a slice keeps its bounds until the next key, so each one is cut out of every frame
+    let mut indices: HashMap<(usize, &str), usize> = HashMap::default();
+    for (frame_number, frame) in sheet.frames.iter().enumerate() {
+        for slice in sheet.meta.slices.iter() {
+            let Some(key) = slice.keys.iter().rev().find(|key| key.frame <= frame_number) else {
+                continue;
+            };
+            let min = vec2(frame.frame.x + key.bounds.x, frame.frame.y + key.bounds.y);
+            let index = atlas.add_texture(Rect::from_corners(min, min + vec2(key.bounds.w, key.bounds.h)));
+            indices.insert((frame_number, slice.name.as_str()), index);
+        }
+    }
+
+    let mut sprites = HashMap::default();
+    let mut clips: HashMap<String, HashMap<AnimationState, AnimationClip>> = HashMap::default();
+    for slice in sheet.meta.slices.iter() {
+        let Some(first_key) = slice.keys.first() else {
+            continue;
+        };
+        if let Some(index) = indices.get(&(first_key.frame, slice.name.as_str())) {
+            sprites.insert(slice.name.clone(), *index);
+        }
+        for tag in sheet.meta.frame_tags.iter() {
+            let Some(state) = animation_state(&tag.name) else {
+                warn!("tag {} is not an animation state", tag.name);
+                continue;
+            };
+            let frames = tag_frames(tag).into_iter()
+                .filter_map(|frame_number| Some(AnimationFrame {
+                    index: *indices.get(&(frame_number, slice.name.as_str()))?,
+                    duration: sheet.frames.get(frame_number)?.duration as f32 / 1000.0,
+                }))
+                .collect();
+            clips.entry(slice.name.clone()).or_default().insert(state, AnimationClip { frames });
+        }
+    }
+
+    (sprites, clips)
+}
+
+impl AssetLoader for AsepriteLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let sheet = serde_json::from_slice::<AsepriteSheet>(bytes)?;
+            let image_path: AssetPath<'static> = load_context.path()
+                .parent()
+                .unwrap_or(Path::new(""))
+                .join(&sheet.meta.image)
+                .into();
+            let texture = load_context.get_handle(image_path.clone());
+            let mut atlas = TextureAtlas::new_empty(texture, vec2(sheet.meta.size.w, sheet.meta.size.h));
+
+            let (sprites, clips) = cut_sheet(&sheet, &mut atlas);
+
+            let atlas = load_context.set_labeled_asset("atlas", LoadedAsset::new(atlas));
+            load_context.set_default_asset(
+                LoadedAsset::new(SpriteSheet { atlas, sprites, clips }).with_dependency(image_path)
+            );
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["aseprite.json"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{prelude::*, math::vec2};
+
+    use crate::{components::AnimationState, plugins::animation::SpriteSheet};
+
+    use super::{AsepriteSheet, AsepriteTag, tag_frames, cut_sheet};
+
+    fn tag(direction: &str) -> AsepriteTag {
+        AsepriteTag {
+            name: "walk".to_string(),
+            from: 2,
+            to: 5,
+            direction: direction.to_string(),
+        }
+    }
+
+    #[test]
+    fn tag_directions_order_frames() {
+        assert_eq!(tag_frames(&tag("forward")), vec![2, 3, 4, 5]);
+        assert_eq!(tag_frames(&tag("reverse")), vec![5, 4, 3, 2]);
+        assert_eq!(tag_frames(&tag("pingpong")), vec![2, 3, 4, 5, 4, 3]);
+    }
+
+    #[test]
+    fn shipped_sheet_names_every_sprite() {
+        let sheet: AsepriteSheet = serde_json::from_str(include_str!("../../assets/sprites.aseprite.json")).unwrap();
+        let names: Vec<&str> = sheet.meta.slices.iter().map(|slice| slice.name.as_str()).collect();
+        for name in ["player", "brawler", "skirmisher", "berserker", "wall", "punch", "slash", "fireball", "coin", "potion", "scroll"] {
+            assert!(names.contains(&name), "missing sprite {}", name);
+        }
+    }
+
+    #[test]
+    fn shipped_sheet_has_a_clip_for_every_state() {
+        let sheet: AsepriteSheet = serde_json::from_str(include_str!("../../assets/sprites.aseprite.json")).unwrap();
+        let mut atlas = TextureAtlas::new_empty(Handle::default(), vec2(sheet.meta.size.w, sheet.meta.size.h));
+        let (sprites, clips) = cut_sheet(&sheet, &mut atlas);
+        let sprite_sheet = SpriteSheet { atlas: Handle::default(), sprites, clips };
+        for name in ["player", "brawler", "skirmisher", "berserker"] {
+            for state in [AnimationState::Idle, AnimationState::Walk, AnimationState::Attack, AnimationState::Hit, AnimationState::Death] {
+                let own = &sprite_sheet.clips[name][&state];
+                assert!(!own.frames.is_empty(), "{} has no {:?} frames", name, state);
+                assert!(std::ptr::eq(sprite_sheet.clip(name, state).unwrap(), own), "{} {:?} falls back to idle", name, state);
+            }
+        }
+    }
+}
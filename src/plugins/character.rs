@@ -1,14 +1,30 @@
 use std::f32::consts::{FRAC_PI_8};
 use bevy::prelude::*;
-use bevy_rapier2d::prelude::{KinematicCharacterController};
+use bevy_rapier2d::prelude::{KinematicCharacterController, ColliderDisabled};
 
-use crate::{GameState, components::{TargetPosition, WiggleEffect, RotateAroundPoint, Character, Health, PlayerControlled, EquippedSkill, AttackCD, HealthBar}, SPRITE_DRAW_SIZE};
+use crate::{GameState, components::{TargetPosition, WiggleEffect, RotateAroundPoint, Character, Health, PlayerControlled, SkillLoadout, Cooldowns, HealthBar, Animation, AnimationState, Knockback, Stunned, Slow, Burn, Poison, Dying, TTL, Stats, RandomWalkAi, AiBehaviour, HOTBAR_SLOTS, SECONDARY_SLOT}, SPRITE_DRAW_SIZE};
 
-use super::events::SkillEvent;
+use super::{events::{SkillEvent, SpawnParticles, Died}, skills::on_skill_used, particles::{DUST, DEATH_BURST}, damage::resolve_damage};
 
 const WIGGLE_SPEED: f32 = 100.0;
+/// Seconds a dead character lingers before it is despawned.
+const DYING_TIME: f32 = 0.6;
 pub const PLAYER_VELOCITY: f32 = 5.0;
+const SLOT_KEYS: [KeyCode; HOTBAR_SLOTS] = [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4];
 
+/// Health bars, kept apart from the characters that own the `Health` they show.
+type HealthBars = (With<HealthBar>, Without<Health>);
+/// Characters whose controller got a new move this frame.
+type MovedCharacters = (Changed<KinematicCharacterController>, With<Character>);
+/// The player while it can act, kept apart from the camera it aims through.
+type ActivePlayer = (With<PlayerControlled>, Without<Camera>, Without<Stunned>);
+
+/// Moving characters, with their wiggle and the clip that follows their movement.
+type Walkers = (Entity, &'static KinematicCharacterController, &'static GlobalTransform, Option<&'static WiggleEffect>, Option<&'static mut Animation>);
+/// Characters heading for a target position, at the speed their stats and slow leave them.
+type Steered = (Entity, &'static TargetPosition, &'static Transform, &'static mut KinematicCharacterController, Option<&'static Stats>, Option<&'static Slow>);
+/// The player while it can move.
+type MobilePlayer = (With<PlayerControlled>, Without<Stunned>);
 
 pub struct PlayerInputPlugin;
 
@@ -17,7 +33,8 @@ impl Plugin for PlayerInputPlugin {
         app
             .add_systems((
                 input,
-                mouse_input,
+                select_skill_slot,
+                mouse_input.after(select_skill_slot).before(on_skill_used),
             )
             .in_set(OnUpdate(GameState::Playing)));
     }
@@ -30,7 +47,8 @@ impl Plugin for MovementPlugin {
         app
         .add_systems((
             move_to_target_position,
-            movement_detection.after(move_to_target_position).after(input),
+            apply_knockback.after(move_to_target_position).after(input),
+            movement_detection.after(apply_knockback),
             update_wiggle_effect,
             update_rotate_around,
             stop_wiggle_effect,
@@ -46,26 +64,45 @@ impl Plugin for CharacterPlugin {
             .add_plugin(PlayerInputPlugin)
             .add_plugin(MovementPlugin)
             .add_systems((
-                cleanup_on_zero_health,
+                handle_deaths.after(resolve_damage),
                 update_health_bar,
             ).in_set(OnUpdate(GameState::Playing)));
     }
 }
 
-fn cleanup_on_zero_health(
+fn handle_deaths(
     mut commands: Commands,
-    health_q: Query<(Entity, &Health), Changed<Health>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut died_events: EventReader<Died>,
+    mut particle_events: EventWriter<SpawnParticles>,
+    mut dead_q: Query<(&GlobalTransform, Option<&PlayerControlled>, Option<&mut Animation>)>,
 ) {
-    for (entity, health) in health_q.iter() {
-        if health.act <= 0.0 {
-            commands.entity(entity).despawn_recursive();
+    for ev in died_events.iter() {
+        let Ok((transform, player_controlled, animation)) = dead_q.get_mut(ev.entity) else {
+            continue;
+        };
+        particle_events.send(SpawnParticles {
+            emitter: DEATH_BURST.to_string(),
+            position: transform.translation().truncate(),
+            direction: Vec2::ZERO,
+        });
+        if let Some(mut animation) = animation {
+            animation.play(AnimationState::Death);
+        }
+        if player_controlled.is_some() {
+            next_state.set(GameState::GameOver);
+        } else {
+            commands.entity(ev.entity)
+                .remove::<(RandomWalkAi, AiBehaviour, TargetPosition, Knockback, Stunned, Burn, Poison, Slow)>()
+                .insert((Dying, TTL::new(DYING_TIME), ColliderDisabled));
         }
     }
 }
 
+
 fn update_health_bar(
     health_q: Query<&Health, Changed<Health>>,
-    mut healthbar_q: Query<(&Parent, &mut Sprite), (With<HealthBar>, Without<Health>)>,
+    mut healthbar_q: Query<(&Parent, &mut Sprite), HealthBars>,
 ) {
     for (parent, mut sprite) in healthbar_q.iter_mut() {
         let Ok(health) = health_q.get(parent.get()) else {
@@ -76,21 +113,35 @@ fn update_health_bar(
     }
 }
 
+fn speed_multiplier(stats: Option<&Stats>, slow: Option<&Slow>) -> f32 {
+    stats.map_or(1.0, |stats| stats.speed) * slow.map_or(1.0, |slow| slow.factor)
+}
+
 fn move_to_target_position(
     mut commands: Commands,
-    mut movable_q: Query<(Entity, &TargetPosition, &Transform, &mut KinematicCharacterController)>,
+    mut movable_q: Query<Steered, Without<Stunned>>,
 ) {
-    for (entity, target_position, transform, mut controller) in movable_q.iter_mut() {
+    for (entity, target_position, transform, mut controller, stats, slow) in movable_q.iter_mut() {
         let delta_v = target_position.0 - transform.translation.truncate();
         if delta_v.length_squared() < 10.0 {
             commands.entity(entity).remove::<TargetPosition>();
             continue;
         }
-        let velocity = delta_v.normalize() * PLAYER_VELOCITY;
+        let velocity = delta_v.normalize() * PLAYER_VELOCITY * speed_multiplier(stats, slow);
         controller.translation = Some(velocity);
     }
 }
 
+fn apply_knockback(
+    time: Res<Time>,
+    mut knockback_q: Query<(&Knockback, &mut KinematicCharacterController)>,
+) {
+    let dt = time.delta_seconds();
+    for (knockback, mut controller) in knockback_q.iter_mut() {
+        controller.translation = Some(knockback.velocity * dt);
+    }
+}
+
 fn update_wiggle_effect(
     mut q: Query<(&mut WiggleEffect, &mut Transform)>,
 ) {
@@ -114,18 +165,28 @@ fn update_rotate_around(
 
 pub fn movement_detection(
     mut commands: Commands,
-    q: Query<(Entity, &KinematicCharacterController, Option<&WiggleEffect>), (Changed<KinematicCharacterController> ,With<Character>)>,
+    mut particle_events: EventWriter<SpawnParticles>,
+    mut q: Query<Walkers, MovedCharacters>,
 ) {
-    for (entity, controller, wiggle_effect) in q.iter() {
-        if controller.translation.is_some() {
+    for (entity, controller, transform, wiggle_effect, animation) in q.iter_mut() {
+        let moving = controller.translation.is_some();
+        if moving {
             if wiggle_effect.is_none() {
                 commands.entity(entity).insert(WiggleEffect(FRAC_PI_8 / 4.0));
+                particle_events.send(SpawnParticles {
+                    emitter: DUST.to_string(),
+                    position: transform.translation().truncate() - Vec2::new(0.0, SPRITE_DRAW_SIZE / 2.0),
+                    direction: Vec2::ZERO,
+                });
             }
         } else {
             if wiggle_effect.is_some() {
                 commands.entity(entity).remove::<WiggleEffect>();
             }
         }
+        if let Some(mut animation) = animation {
+            animation.set_base(if moving { AnimationState::Walk } else { AnimationState::Idle });
+        }
     }
 }
 
@@ -144,9 +205,9 @@ fn stop_wiggle_effect(
 
 fn input(
     keyboard_input: Res<Input<KeyCode>>,
-    mut player_q: Query<&mut KinematicCharacterController, With<PlayerControlled>>,
+    mut player_q: Query<(&mut KinematicCharacterController, Option<&Stats>, Option<&Slow>), MobilePlayer>,
 ) {
-    let Ok(mut controller) = player_q.get_single_mut() else {
+    let Ok((mut controller, stats, slow)) = player_q.get_single_mut() else {
         return;
     };
 
@@ -165,7 +226,21 @@ fn input(
     }
 
     if velocity != Vec2::ZERO {
-        controller.translation = Some(velocity.normalize() * PLAYER_VELOCITY);
+        controller.translation = Some(velocity.normalize() * PLAYER_VELOCITY * speed_multiplier(stats, slow));
+    }
+}
+
+fn select_skill_slot(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut player_q: Query<&mut SkillLoadout, With<PlayerControlled>>,
+) {
+    let Ok(mut loadout) = player_q.get_single_mut() else {
+        return;
+    };
+    for (slot, key) in SLOT_KEYS.iter().enumerate() {
+        if keyboard_input.just_pressed(*key) && loadout.slots[slot].is_some() {
+            loadout.active = slot;
+        }
     }
 }
 
@@ -174,9 +249,9 @@ fn mouse_input(
     window: Query<&Window>,
     camera_q: Query<(&Camera, &GlobalTransform)>,
     mut skill_events: EventWriter<SkillEvent>,
-    mut player_q: Query<(Entity, &Transform, &KinematicCharacterController, Option<&EquippedSkill>), (With<PlayerControlled>, Without<Camera>, Without<AttackCD>)>,
+    player_q: Query<(Entity, &Transform, &KinematicCharacterController, &SkillLoadout, &Cooldowns), ActivePlayer>,
 ) {
-    let Ok((entity, transform, controller, equipped_skill)) = player_q.get_single_mut() else {
+    let Ok((entity, transform, controller, loadout, cooldowns)) = player_q.get_single() else {
         return;
     };
     let Ok(window) = window.get_single() else {
@@ -192,20 +267,25 @@ fn mouse_input(
         return;
     };
 
-    let Some(equipped_skill) = equipped_skill else {
+    let slot = if mouse_button_input.pressed(MouseButton::Left) {
+        loadout.active
+    } else if mouse_button_input.pressed(MouseButton::Right) {
+        SECONDARY_SLOT
+    } else {
+        return;
+    };
+    let Some(skill) = loadout.slots[slot].as_ref().filter(|skill| cooldowns.is_ready(skill)) else {
         return;
     };
 
-    if mouse_button_input.pressed(MouseButton::Left) {
-        let spawn_vector = (mouse_position - transform.translation.truncate()).normalize();
-        let angle = spawn_vector.y.atan2(spawn_vector.x);
-        skill_events.send(SkillEvent {
-            kind: equipped_skill.clone(),
-            parent: entity,
-            angle,
-            //magic value so that when player moves forward the attack starts litte bit infront and not otherwise
-            start_position: transform.translation.truncate() + controller.translation.unwrap_or(Vec2::ZERO) * 4.0,
-            spawn_vector_norm: spawn_vector
-        });
-    }
+    let spawn_vector = (mouse_position - transform.translation.truncate()).normalize();
+    let angle = spawn_vector.y.atan2(spawn_vector.x);
+    skill_events.send(SkillEvent {
+        skill: skill.clone(),
+        parent: entity,
+        angle,
+        //magic value so that when player moves forward the attack starts litte bit infront and not otherwise
+        start_position: transform.translation.truncate() + controller.translation.unwrap_or(Vec2::ZERO) * 4.0,
+        spawn_vector_norm: spawn_vector
+    });
 }
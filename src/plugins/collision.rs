@@ -1,54 +1,416 @@
-use bevy::prelude::*;
-use bevy_rapier2d::prelude::CollisionEvent;
+use bevy::{prelude::*, utils::HashSet, ecs::system::SystemParam};
+use bevy_rapier2d::prelude::{CollisionEvent, Velocity};
 
-use crate::{GameState, components::{Attack, Wall, Health}};
+use crate::{
+    GameState,
+    components::{
+        Attack, Wall, Health, AttackOwner, Faction, Animation, AnimationState, Knockback, Invulnerable, HitFlash,
+        HitTargets, Piercing, Bouncing,
+    },
+};
+
+use super::{events::{DamageEvent, ApplyStatusEffect, ProjectileImpact, SpawnParticles}, particles::SPARKS};
+
+/// Seconds a character ignores further hits after being hit.
+const INVULNERABILITY_TIME: f32 = 0.4;
+/// Seconds the knockback distance is covered in.
+const KNOCKBACK_TIME: f32 = 0.15;
 
 pub struct CollisionPlugin;
 
 impl Plugin for CollisionPlugin {
     fn build(&self, app: &mut App) {
         app
+            .init_resource::<FactionRelations>()
             .add_systems((
                 handle_events,
             ).in_set(OnUpdate(GameState::Playing)));
     }
 }
 
-fn handle_events(
+/// Pairs of factions whose attacks damage each other. Relations are symmetric.
+#[derive(Resource)]
+pub struct FactionRelations {
+    hostile: HashSet<(Faction, Faction)>,
+}
+
+impl FactionRelations {
+    pub fn set_hostile(&mut self, a: Faction, b: Faction, hostile: bool) {
+        if hostile {
+            self.hostile.insert((a, b));
+            self.hostile.insert((b, a));
+        } else {
+            self.hostile.remove(&(a, b));
+            self.hostile.remove(&(b, a));
+        }
+    }
+
+    pub fn is_hostile(&self, a: Faction, b: Faction) -> bool {
+        self.hostile.contains(&(a, b))
+    }
+}
+
+impl Default for FactionRelations {
+    fn default() -> Self {
+        let mut relations = Self { hostile: HashSet::default() };
+        relations.set_hostile(Faction::Player, Faction::Enemies, true);
+        relations
+    }
+}
+
+/// Sends the projectile back the way it came along the side of the wall tile it hit.
+fn bounce(velocity: &mut Velocity, transform: &mut Transform, wall_position: Vec2) {
+    let offset = transform.translation.truncate() - wall_position;
+    let normal = if offset.x.abs() > offset.y.abs() {
+        Vec2::new(offset.x.signum(), 0.0)
+    } else {
+        Vec2::new(0.0, offset.y.signum())
+    };
+    let along_normal = velocity.linvel.dot(normal);
+    if along_normal >= 0.0 {
+        return;
+    }
+    let reflected = velocity.linvel - 2.0 * along_normal * normal;
+    transform.rotate_z(velocity.linvel.angle_between(reflected));
+    velocity.linvel = reflected;
+}
+
+/// The attack side of a collision, with whatever makes projectiles keep going.
+type Attacks = (
+    &'static Attack,
+    Option<&'static AttackOwner>,
+    Option<&'static mut HitTargets>,
+    Option<&'static mut Piercing>,
+    Option<&'static mut Bouncing>,
+    Option<&'static mut Velocity>,
+);
+
+/// What an attack may have run into, looked up by the entity it collided with.
+#[derive(SystemParam)]
+pub struct CollisionTargets<'w, 's> {
+    transform_q: Query<'w, 's, &'static mut Transform>,
+    invulnerable_q: Query<'w, 's, (), With<Invulnerable>>,
+    faction_q: Query<'w, 's, &'static Faction>,
+    wall_q: Query<'w, 's, Entity, With<Wall>>,
+    health_q: Query<'w, 's, Option<&'static mut Animation>, With<Health>>,
+}
+
+/// Everything a hit sets off.
+#[derive(SystemParam)]
+pub struct HitEvents<'w> {
+    damage: EventWriter<'w, DamageEvent>,
+    status: EventWriter<'w, ApplyStatusEffect>,
+    impact: EventWriter<'w, ProjectileImpact>,
+    particles: EventWriter<'w, SpawnParticles>,
+}
+
+pub fn handle_events(
     mut commands: Commands,
     mut collision_events: EventReader<CollisionEvent>,
+    mut hit_events: HitEvents,
     // mut contact_force_events: EventReader<ContactForceEvent>,
-    attack_q: Query<&Attack>,
-    wall_q: Query<Entity, With<Wall>>,
-    mut health_q: Query<&mut Health>,
+    relations: Res<FactionRelations>,
+    mut attack_q: Query<Attacks>,
+    mut targets: CollisionTargets,
 ) {
     for collision_event in collision_events.iter() {
-        match collision_event {
-            CollisionEvent::Started(e1, e2, _) => {
-                let mut attack_e = *e1;
-                let mut other = *e2;
-                let Ok(attack) = attack_q.get(attack_e).or_else(|_| {
-                    other = *e1;
-                    attack_e = *e2;
-                    attack_q.get(attack_e)
-                }) else {
+        let CollisionEvent::Started(e1, e2, _) = collision_event else {
+            continue;
+        };
+        let (attack_e, other) = if attack_q.contains(*e1) { (*e1, *e2) } else { (*e2, *e1) };
+        let Ok((attack, owner, mut hit_targets, piercing, bouncing, velocity)) = attack_q.get_mut(attack_e) else {
+            continue;
+        };
+        if let Some(owner) = owner {
+            if owner.entity == other {
+                continue;
+            }
+            if let Ok(faction) = targets.faction_q.get(other) {
+                if !relations.is_hostile(owner.faction, *faction) {
                     continue;
-                };
-                if let Ok(_) =  wall_q.get(other) {
-                    info!("WALL HIT");
                 }
+            }
+        }
+        let attack_position = targets.transform_q.get(attack_e).map(|transform| transform.translation.truncate());
+        let other_position = targets.transform_q.get(other).map(|transform| transform.translation.truncate());
 
-                if let Ok(mut health) =  health_q.get_mut(other) {
-                    health.act -= attack.value;
-                    info!("HIT someone with health, current health is {}", health.act);
+        if targets.wall_q.get(other).is_ok() {
+            info!("WALL HIT");
+            if let Some(mut bouncing) = bouncing.filter(|bouncing| bouncing.0 > 0) {
+                if let (Some(mut velocity), Ok(mut transform), Ok(wall_position)) = (velocity, targets.transform_q.get_mut(attack_e), other_position) {
+                    bouncing.0 -= 1;
+                    bounce(&mut velocity, &mut transform, wall_position);
+                    continue;
                 }
-                commands.entity(attack_e).despawn_recursive();
-            },
-            _ => {}
+            }
+        } else if hit_targets.as_ref().is_some_and(|hit_targets| hit_targets.0.contains(&other)) {
+            // a projectile never hits the same target twice
+            continue;
+        }
+
+        if let (Ok(attack_position), Ok(other_position)) = (attack_position, other_position) {
+            hit_events.particles.send(SpawnParticles {
+                emitter: SPARKS.to_string(),
+                position: attack_position,
+                direction: attack_position - other_position,
+            });
+        }
+
+        let is_character = targets.health_q.contains(other);
+        let damaged = is_character && !targets.invulnerable_q.contains(other);
+        if damaged {
+            let source = owner.map_or(attack_e, |owner| owner.entity);
+            hit_events.damage.send(DamageEvent {
+                source,
+                target: other,
+                amount: attack.value,
+                kind: attack.kind,
+            });
+            if let Ok(Some(mut animation)) = targets.health_q.get_mut(other) {
+                animation.play(AnimationState::Hit);
+            }
+
+            let direction = match (attack_position, other_position) {
+                (Ok(attack_position), Ok(target_position)) => (target_position - attack_position).normalize_or_zero(),
+                _ => Vec2::ZERO,
+            };
+            let mut target = commands.entity(other);
+            target.insert((
+                Invulnerable::new(INVULNERABILITY_TIME),
+                HitFlash::new(INVULNERABILITY_TIME),
+            ));
+            if attack.knockback > 0.0 {
+                target.insert(Knockback::new(direction * attack.knockback / KNOCKBACK_TIME, KNOCKBACK_TIME));
+            }
+            for effect in attack.effects.iter() {
+                hit_events.status.send(ApplyStatusEffect {
+                    source,
+                    target: other,
+                    effect: *effect,
+                });
+            }
         }
+        if is_character {
+            if let Some(mut piercing) = piercing.filter(|piercing| piercing.0 > 0) {
+                // only a hit that dealt damage counts, an invulnerable target is passed through for free
+                if damaged {
+                    if let Some(hit_targets) = hit_targets.as_mut() {
+                        hit_targets.0.insert(other);
+                    }
+                    piercing.0 -= 1;
+                }
+                continue;
+            }
+        }
+        hit_events.impact.send(ProjectileImpact { attack: attack_e, target: other });
+        commands.entity(attack_e).despawn_recursive();
     }
 
     // for contact_force_event in contact_force_events.iter() {
     //     println!("Received contact force event: {:?}", contact_force_event);
     // }
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+    use bevy_rapier2d::{prelude::{CollisionEvent, Velocity}, rapier::geometry::CollisionEventFlags};
+
+    use crate::{
+        GameState,
+        components::{Attack, AttackOwner, Character, Faction, Health, DamageKind, HitTargets, Piercing, Bouncing, Invulnerable, Wall},
+        plugins::{events::{DamageEvent, DamageApplied, ApplyStatusEffect, ProjectileImpact, SpawnParticles, Died}, damage::DamagePlugin, rng::GameRng},
+    };
+
+    use super::{CollisionPlugin, FactionRelations};
+
+    fn test_app() -> App {
+        let mut app = App::new();
+        app
+            .add_plugins(MinimalPlugins)
+            .add_state::<GameState>()
+            .add_event::<CollisionEvent>()
+            .add_event::<DamageEvent>()
+            .add_event::<DamageApplied>()
+            .add_event::<ApplyStatusEffect>()
+            .add_event::<ProjectileImpact>()
+            .add_event::<SpawnParticles>()
+            .add_event::<Died>()
+            .insert_resource(GameRng::from_seed(0))
+            .add_plugin(CollisionPlugin)
+            .add_plugin(DamagePlugin);
+        app.world.insert_resource(State(GameState::Playing));
+        app
+    }
+
+    fn spawn_character(app: &mut App, faction: Faction) -> Entity {
+        app.world.spawn((
+            Character,
+            faction,
+            Health { act: 3.0, max: 3.0 },
+        )).id()
+    }
+
+    fn spawn_attack(app: &mut App, owner: Entity) -> Entity {
+        let faction = *app.world.get::<Faction>(owner).unwrap();
+        app.world.spawn((
+            Attack { value: 1.0, kind: DamageKind::Physical, knockback: 0.0, effects: vec![] },
+            AttackOwner { entity: owner, faction },
+        )).id()
+    }
+
+    fn collide(app: &mut App, attack: Entity, other: Entity) {
+        app.world.send_event(CollisionEvent::Started(attack, other, CollisionEventFlags::SENSOR));
+        app.update();
+    }
+
+    fn hit(app: &mut App, owner: Entity, target: Entity) {
+        let attack = spawn_attack(app, owner);
+        collide(app, attack, target);
+    }
+
+    fn health(app: &App, entity: Entity) -> f32 {
+        app.world.get::<Health>(entity).unwrap().act
+    }
+
+    #[test]
+    fn player_attack_damages_enemy() {
+        let mut app = test_app();
+        let player = spawn_character(&mut app, Faction::Player);
+        let enemy = spawn_character(&mut app, Faction::Enemies);
+
+        hit(&mut app, player, enemy);
+
+        assert_eq!(health(&app, player), 3.0);
+        assert_eq!(health(&app, enemy), 2.0);
+    }
+
+    #[test]
+    fn attack_does_not_damage_its_owner() {
+        let mut app = test_app();
+        let player = spawn_character(&mut app, Faction::Player);
+        let enemy = spawn_character(&mut app, Faction::Enemies);
+
+        hit(&mut app, player, player);
+        hit(&mut app, enemy, enemy);
+
+        assert_eq!(health(&app, player), 3.0);
+        assert_eq!(health(&app, enemy), 3.0);
+    }
+
+    #[test]
+    fn enemies_do_not_damage_each_other() {
+        let mut app = test_app();
+        let player = spawn_character(&mut app, Faction::Player);
+        let enemy = spawn_character(&mut app, Faction::Enemies);
+        let other_enemy = spawn_character(&mut app, Faction::Enemies);
+
+        hit(&mut app, enemy, other_enemy);
+        hit(&mut app, enemy, player);
+
+        assert_eq!(health(&app, other_enemy), 3.0);
+        assert_eq!(health(&app, player), 2.0);
+    }
+
+    #[test]
+    fn hit_target_is_briefly_invulnerable() {
+        let mut app = test_app();
+        let player = spawn_character(&mut app, Faction::Player);
+        let enemy = spawn_character(&mut app, Faction::Enemies);
+
+        hit(&mut app, player, enemy);
+        hit(&mut app, player, enemy);
+
+        assert_eq!(health(&app, enemy), 2.0);
+    }
+
+    #[test]
+    fn neutral_is_damaged_only_when_configured_hostile() {
+        let mut app = test_app();
+        let player = spawn_character(&mut app, Faction::Player);
+        let neutral = spawn_character(&mut app, Faction::Neutral);
+
+        hit(&mut app, player, neutral);
+        assert_eq!(health(&app, neutral), 3.0);
+
+        app.world.resource_mut::<FactionRelations>().set_hostile(Faction::Player, Faction::Neutral, true);
+        hit(&mut app, player, neutral);
+        assert_eq!(health(&app, neutral), 2.0);
+    }
+
+    #[test]
+    fn final_blow_reports_the_killer_once() {
+        let mut app = test_app();
+        let player = spawn_character(&mut app, Faction::Player);
+        let enemy = spawn_character(&mut app, Faction::Enemies);
+
+        for _ in 0..4 {
+            hit(&mut app, player, enemy);
+            app.world.entity_mut(enemy).remove::<Invulnerable>();
+        }
+
+        let died = app.world.resource::<Events<Died>>();
+        let deaths: Vec<_> = died.get_reader().iter(died).map(|ev| (ev.entity, ev.killer)).collect();
+        assert_eq!(deaths, vec![(enemy, player)]);
+    }
+
+    #[test]
+    fn piercing_projectile_hits_each_target_once() {
+        let mut app = test_app();
+        let player = spawn_character(&mut app, Faction::Player);
+        let enemy = spawn_character(&mut app, Faction::Enemies);
+        let other_enemy = spawn_character(&mut app, Faction::Enemies);
+        let attack = spawn_attack(&mut app, player);
+        app.world.entity_mut(attack).insert((HitTargets::default(), Piercing(1)));
+
+        collide(&mut app, attack, enemy);
+        app.world.entity_mut(enemy).remove::<Invulnerable>();
+        collide(&mut app, attack, enemy);
+        assert_eq!(health(&app, enemy), 2.0);
+        assert!(app.world.get_entity(attack).is_some());
+
+        collide(&mut app, attack, other_enemy);
+        assert_eq!(health(&app, other_enemy), 2.0);
+        assert!(app.world.get_entity(attack).is_none());
+    }
+
+    #[test]
+    fn piercing_projectile_passes_invulnerable_targets_for_free() {
+        let mut app = test_app();
+        let player = spawn_character(&mut app, Faction::Player);
+        let enemy = spawn_character(&mut app, Faction::Enemies);
+        let attack = spawn_attack(&mut app, player);
+        app.world.entity_mut(attack).insert((HitTargets::default(), Piercing(1)));
+        app.world.entity_mut(enemy).insert(Invulnerable::new(1.0));
+
+        collide(&mut app, attack, enemy);
+        assert_eq!(health(&app, enemy), 3.0);
+        assert_eq!(app.world.get::<Piercing>(attack).unwrap().0, 1);
+        assert!(!app.world.get::<HitTargets>(attack).unwrap().0.contains(&enemy));
+
+        app.world.entity_mut(enemy).remove::<Invulnerable>();
+        collide(&mut app, attack, enemy);
+        assert_eq!(health(&app, enemy), 2.0);
+        assert_eq!(app.world.get::<Piercing>(attack).unwrap().0, 0);
+    }
+
+    #[test]
+    fn bouncing_projectile_reflects_off_walls() {
+        let mut app = test_app();
+        let player = spawn_character(&mut app, Faction::Player);
+        let wall = app.world.spawn((Wall, Transform::from_xyz(10.0, 0.0, 0.0))).id();
+        let attack = spawn_attack(&mut app, player);
+        app.world.entity_mut(attack).insert((
+            Bouncing(1),
+            Transform::default(),
+            Velocity { linvel: Vec2::new(100.0, 20.0), ..default() },
+        ));
+
+        collide(&mut app, attack, wall);
+        assert_eq!(app.world.get::<Velocity>(attack).unwrap().linvel, Vec2::new(-100.0, 20.0));
+        assert_eq!(app.world.get::<Bouncing>(attack).unwrap().0, 0);
+
+        collide(&mut app, attack, wall);
+        assert!(app.world.get_entity(attack).is_none());
+    }
+}
@@ -0,0 +1,108 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{GameState, components::{Health, Armor, Resistances, CriticalHit, DamageModifiers, DamageKind, Stats}};
+
+use super::{events::{DamageEvent, DamageApplied, Died}, collision::handle_events, rng::GameRng};
+
+/// Characters taking a hit, with everything on their side that changes how much it hurts.
+type DamageTargets = (&'static mut Health, Option<&'static Armor>, Option<&'static Resistances>, Option<&'static DamageModifiers>);
+
+pub struct DamagePlugin;
+
+impl Plugin for DamagePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_system(resolve_damage.after(handle_events).in_set(OnUpdate(GameState::Playing)));
+    }
+}
+
+/// Target's side of a hit, everything that reduces or amplifies the damage it takes.
+#[derive(Default)]
+pub struct Defenses<'a> {
+    pub armor: Option<&'a Armor>,
+    pub resistances: Option<&'a Resistances>,
+    pub modifiers: Option<&'a DamageModifiers>,
+}
+
+/// Final damage of a hit: the attacker's multiplier and crit go first, then armor for physical hits,
+/// the resistance to the damage kind and the target's multiplier.
+pub fn mitigated_damage(amount: f32, kind: DamageKind, dealt_multiplier: f32, critical_multiplier: f32, defenses: &Defenses) -> f32 {
+    let mut amount = amount * dealt_multiplier * critical_multiplier;
+    if kind == DamageKind::Physical {
+        amount -= defenses.armor.map_or(0.0, |armor| armor.0);
+    }
+    let resistance = defenses.resistances
+        .and_then(|resistances| resistances.0.get(&kind).copied())
+        .unwrap_or(0.0);
+    amount *= 1.0 - resistance;
+    amount *= defenses.modifiers.map_or(1.0, |modifiers| modifiers.taken);
+    amount.max(0.0)
+}
+
+pub fn resolve_damage(
+    mut rng: ResMut<GameRng>,
+    mut damage_events: EventReader<DamageEvent>,
+    mut damage_applied: EventWriter<DamageApplied>,
+    mut died_events: EventWriter<Died>,
+    attacker_q: Query<(Option<&CriticalHit>, Option<&DamageModifiers>, Option<&Stats>)>,
+    mut target_q: Query<DamageTargets>,
+) {
+    for ev in damage_events.iter() {
+        let (critical_hit, dealt_modifiers, stats) = attacker_q.get(ev.source).unwrap_or((None, None, None));
+        let critical = critical_hit.filter(|critical_hit| rng.gen_bool(critical_hit.chance.clamp(0.0, 1.0) as f64));
+        let dealt_multiplier = dealt_modifiers.map_or(1.0, |modifiers| modifiers.dealt) * stats.map_or(1.0, |stats| stats.strength);
+
+        let Ok((mut health, armor, resistances, modifiers)) = target_q.get_mut(ev.target) else {
+            continue;
+        };
+        let defenses = Defenses { armor, resistances, modifiers };
+        let amount = mitigated_damage(
+            ev.amount,
+            ev.kind,
+            dealt_multiplier,
+            critical.map_or(1.0, |critical| critical.multiplier),
+            &defenses,
+        );
+        let was_alive = health.act > 0.0;
+        health.act -= amount;
+        info!("HIT someone with health, current health is {}", health.act);
+        if was_alive && health.act <= 0.0 {
+            died_events.send(Died { entity: ev.target, killer: ev.source });
+        }
+        damage_applied.send(DamageApplied {
+            source: ev.source,
+            target: ev.target,
+            amount,
+            kind: ev.kind,
+            critical: critical.is_some(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::utils::HashMap;
+
+    use crate::components::{Armor, Resistances, DamageModifiers, DamageKind};
+
+    use super::{mitigated_damage, Defenses};
+
+    #[test]
+    fn armor_only_stops_physical_damage() {
+        let armor = Armor(0.5);
+        let defenses = Defenses { armor: Some(&armor), ..Default::default() };
+        assert_eq!(mitigated_damage(2.0, DamageKind::Physical, 1.0, 1.0, &defenses), 1.5);
+        assert_eq!(mitigated_damage(2.0, DamageKind::Fire, 1.0, 1.0, &defenses), 2.0);
+        assert_eq!(mitigated_damage(0.25, DamageKind::Physical, 1.0, 1.0, &defenses), 0.0);
+    }
+
+    #[test]
+    fn resistances_crits_and_modifiers_stack() {
+        let resistances = Resistances(HashMap::from_iter([(DamageKind::Fire, 0.5)]));
+        let modifiers = DamageModifiers { dealt: 1.0, taken: 1.5 };
+        let defenses = Defenses { resistances: Some(&resistances), modifiers: Some(&modifiers), ..Default::default() };
+        assert_eq!(mitigated_damage(2.0, DamageKind::Fire, 1.0, 2.0, &defenses), 3.0);
+        assert_eq!(mitigated_damage(2.0, DamageKind::Physical, 0.5, 1.0, &defenses), 1.5);
+    }
+}
@@ -1,20 +1,88 @@
 use bevy::prelude::*;
 
-use crate::components::EquippedSkill;
+use crate::components::{DamageKind, StatusEffect};
 
 pub struct EventsPlugin;
 
 impl Plugin for EventsPlugin {
     fn build(&self, app: &mut App) {
         app
-          .add_event::<SkillEvent>();
+          .add_event::<SkillEvent>()
+          .add_event::<ChangeLevel>()
+          .add_event::<WaveStarted>()
+          .add_event::<WaveCleared>()
+          .add_event::<DamageEvent>()
+          .add_event::<DamageApplied>()
+          .add_event::<ApplyStatusEffect>()
+          .add_event::<ProjectileImpact>()
+          .add_event::<SpawnParticles>()
+          .add_event::<Died>()
+          .add_event::<Healed>();
     }
 }
 
 pub struct SkillEvent {
-    pub kind: EquippedSkill,
+    pub skill: String,
     pub parent: Entity,
     pub angle: f32,
     pub start_position: Vec2,
     pub spawn_vector_norm: Vec2,
 }
+
+/// Tears down the running level and builds the one at the given index.
+pub struct ChangeLevel(pub usize);
+
+pub struct WaveStarted {
+    pub wave: usize,
+}
+
+pub struct WaveCleared {
+    pub wave: usize,
+}
+
+/// Damage dealt by `source` before the target's defenses are taken into account.
+pub struct DamageEvent {
+    pub source: Entity,
+    pub target: Entity,
+    pub amount: f32,
+    pub kind: DamageKind,
+}
+
+/// Damage that was actually taken off the target's health.
+pub struct DamageApplied {
+    pub source: Entity,
+    pub target: Entity,
+    pub amount: f32,
+    pub kind: DamageKind,
+    pub critical: bool,
+}
+
+/// Health of `entity` dropped to zero from the hit `killer` dealt.
+pub struct Died {
+    pub entity: Entity,
+    pub killer: Entity,
+}
+
+pub struct Healed {
+    pub target: Entity,
+    pub amount: f32,
+}
+
+pub struct ApplyStatusEffect {
+    pub source: Entity,
+    pub target: Entity,
+    pub effect: StatusEffect,
+}
+
+/// Burst of the named emitter, particles fly around `direction` or all around when it is zero.
+pub struct SpawnParticles {
+    pub emitter: String,
+    pub position: Vec2,
+    pub direction: Vec2,
+}
+
+/// Attack was spent on `target` and is despawned at the end of the frame.
+pub struct ProjectileImpact {
+    pub attack: Entity,
+    pub target: Entity,
+}
@@ -0,0 +1,239 @@
+use bevy::{prelude::*, math::vec2, ecs::system::SystemParam};
+use bevy_rapier2d::prelude::{CollisionEvent, RapierContext, QueryFilter, RigidBody, Sensor, Collider, ActiveEvents};
+
+use crate::{
+    GameState,
+    GameResources,
+    SPRITE_DRAW_SIZE,
+    components::{Attack, AttackOwner, Explosive, Explosion, Faction, Health, HitTargets, Animation, AnimationState, Knockback, HitFlash, TTL},
+};
+
+use super::{
+    events::{ProjectileImpact, DamageEvent, ApplyStatusEffect},
+    collision::{FactionRelations, handle_events},
+    damage::resolve_damage,
+    timers::update_ttl,
+};
+
+const EXPLOSION_Z_INDEX: f32 = 2.0;
+const EXPLOSION_LIFETIME: f32 = 0.3;
+const EXPLOSION_SPRITE: &str = "fireball";
+const EXPLOSION_COLOR: Color = Color::rgb(1.0, 0.55, 0.2);
+/// The flash starts at this share of the radius and grows to the full size.
+const EXPLOSION_START_SIZE: f32 = 0.4;
+/// Seconds the knockback distance is covered in.
+const KNOCKBACK_TIME: f32 = 0.2;
+const FLASH_TIME: f32 = 0.2;
+
+pub struct ExplosionPlugin;
+
+impl Plugin for ExplosionPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems((
+                detonate_explosives.after(handle_events).after(update_ttl),
+                explosion_hits.before(resolve_damage),
+                animate_explosions,
+            ).in_set(OnUpdate(GameState::Playing)));
+    }
+}
+
+/// Damage at `distance` from the center, dropping linearly by `falloff` toward the edge.
+pub fn explosion_damage(damage: f32, distance: f32, radius: f32, falloff: f32) -> f32 {
+    let edge = (distance / radius).clamp(0.0, 1.0);
+    damage * (1.0 - falloff.clamp(0.0, 1.0) * edge)
+}
+
+/// Walls between the center and the target shield it, the wall the explosion went off against doesn't count.
+fn occluded(rapier_context: &RapierContext, center: Vec2, offset: Vec2) -> bool {
+    let distance = offset.length();
+    if distance <= f32::EPSILON {
+        return false;
+    }
+    let filter = QueryFilter::only_fixed().exclude_sensors();
+    let mut containing = Vec::new();
+    rapier_context.intersections_with_point(center, filter, |entity| {
+        containing.push(entity);
+        true
+    });
+    let predicate = |entity| !containing.contains(&entity);
+    rapier_context.cast_ray(center, offset / distance, distance, true, filter.predicate(&predicate)).is_some()
+}
+
+fn detonate_explosives(
+    mut commands: Commands,
+    game_resources: Res<GameResources>,
+    mut impact_events: EventReader<ProjectileImpact>,
+    explosive_q: Query<(Entity, &Explosive, &Attack, &AttackOwner, &Transform, &TTL)>,
+) {
+    let impacts: Vec<Entity> = impact_events.iter().map(|ev| ev.attack).collect();
+    for (entity, explosive, attack, owner, transform, ttl) in explosive_q.iter() {
+        if !ttl.0.finished() && !impacts.contains(&entity) {
+            continue;
+        }
+        let radius = explosive.radius * SPRITE_DRAW_SIZE;
+        commands.spawn((
+            Explosion {
+                owner: *owner,
+                damage: explosive.damage,
+                kind: attack.kind,
+                radius,
+                falloff: explosive.falloff,
+                knockback: explosive.knockback,
+                effects: explosive.effects.clone(),
+            },
+            HitTargets::default(),
+            TTL::new(EXPLOSION_LIFETIME),
+            SpriteSheetBundle {
+                sprite: TextureAtlasSprite {
+                    custom_size: Some(vec2(radius, radius) * 2.0 * EXPLOSION_START_SIZE),
+                    color: EXPLOSION_COLOR,
+                    ..default()
+                },
+                texture_atlas: game_resources.atlas_handle.clone(),
+                transform: Transform::from_translation(transform.translation.truncate().extend(EXPLOSION_Z_INDEX)),
+                ..default()
+            },
+            Animation::new(EXPLOSION_SPRITE),
+            RigidBody::Dynamic,
+            Sensor,
+            Collider::ball(radius),
+            ActiveEvents::COLLISION_EVENTS,
+        ));
+    }
+}
+
+/// Whom a blast may hurt and whether a wall is in the way.
+#[derive(SystemParam)]
+struct BlastReach<'w> {
+    rapier_context: Res<'w, RapierContext>,
+    relations: Res<'w, FactionRelations>,
+}
+
+/// Characters a blast can hit, with the clip to play when it does.
+type BlastTargets = (&'static Transform, Option<&'static Faction>, Option<&'static mut Animation>);
+
+fn explosion_hits(
+    mut commands: Commands,
+    reach: BlastReach,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut status_events: EventWriter<ApplyStatusEffect>,
+    mut explosion_q: Query<(&Explosion, &Transform, &mut HitTargets)>,
+    mut target_q: Query<BlastTargets, With<Health>>,
+) {
+    for collision_event in collision_events.iter() {
+        let CollisionEvent::Started(e1, e2, _) = collision_event else {
+            continue;
+        };
+        let (explosion_e, other) = if explosion_q.contains(*e1) { (*e1, *e2) } else { (*e2, *e1) };
+        let (Ok((explosion, explosion_transform, mut hit_targets)), Ok((target_transform, faction, animation))) = (explosion_q.get_mut(explosion_e), target_q.get_mut(other)) else {
+            continue;
+        };
+        if explosion.owner.entity == other || faction.is_some_and(|faction| !reach.relations.is_hostile(explosion.owner.faction, *faction)) {
+            continue;
+        }
+        // leaving and entering the blast again does not hit twice
+        if hit_targets.0.contains(&other) {
+            continue;
+        }
+        let center = explosion_transform.translation.truncate();
+        let offset = target_transform.translation.truncate() - center;
+        if occluded(&reach.rapier_context, center, offset) {
+            continue;
+        }
+
+        hit_targets.0.insert(other);
+        damage_events.send(DamageEvent {
+            source: explosion.owner.entity,
+            target: other,
+            amount: explosion_damage(explosion.damage, offset.length(), explosion.radius, explosion.falloff),
+            kind: explosion.kind,
+        });
+        if let Some(mut animation) = animation {
+            animation.play(AnimationState::Hit);
+        }
+        let mut target = commands.entity(other);
+        target.insert(HitFlash::new(FLASH_TIME));
+        if explosion.knockback > 0.0 {
+            target.insert(Knockback::new(offset.normalize_or_zero() * explosion.knockback / KNOCKBACK_TIME, KNOCKBACK_TIME));
+        }
+        for effect in explosion.effects.iter() {
+            status_events.send(ApplyStatusEffect {
+                source: explosion.owner.entity,
+                target: other,
+                effect: *effect,
+            });
+        }
+    }
+}
+
+/// The flash grows to the full radius and fades out over its `TTL`.
+fn animate_explosions(
+    mut explosion_q: Query<(&Explosion, &TTL, &mut TextureAtlasSprite)>,
+) {
+    for (explosion, ttl, mut sprite) in explosion_q.iter_mut() {
+        let progress = ttl.0.percent();
+        let size = explosion.radius * 2.0 * (EXPLOSION_START_SIZE + (1.0 - EXPLOSION_START_SIZE) * progress);
+        sprite.custom_size = Some(vec2(size, size));
+        sprite.color.set_a(1.0 - progress);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+    use bevy_rapier2d::{prelude::{CollisionEvent, RapierContext}, rapier::geometry::CollisionEventFlags};
+
+    use crate::{
+        components::{AttackOwner, Explosion, Faction, Health, DamageKind, HitTargets},
+        plugins::{events::{DamageEvent, ApplyStatusEffect}, collision::FactionRelations},
+    };
+
+    use super::{explosion_damage, explosion_hits};
+
+    #[test]
+    fn damage_falls_off_toward_the_edge() {
+        assert_eq!(explosion_damage(2.0, 0.0, 10.0, 0.5), 2.0);
+        assert_eq!(explosion_damage(2.0, 5.0, 10.0, 0.5), 1.5);
+        assert_eq!(explosion_damage(2.0, 10.0, 10.0, 0.5), 1.0);
+        assert_eq!(explosion_damage(2.0, 20.0, 10.0, 0.5), 1.0);
+        assert_eq!(explosion_damage(2.0, 10.0, 10.0, 0.0), 2.0);
+    }
+
+    #[test]
+    fn target_entering_the_blast_again_is_not_hit_twice() {
+        let mut app = App::new();
+        app
+            .add_event::<CollisionEvent>()
+            .add_event::<DamageEvent>()
+            .add_event::<ApplyStatusEffect>()
+            .init_resource::<RapierContext>()
+            .init_resource::<FactionRelations>()
+            .add_system(explosion_hits);
+        let player = app.world.spawn(Faction::Player).id();
+        let enemy = app.world.spawn((Faction::Enemies, Health { act: 3.0, max: 3.0 }, Transform::from_xyz(10.0, 0.0, 0.0))).id();
+        let explosion = app.world.spawn((
+            Explosion {
+                owner: AttackOwner { entity: player, faction: Faction::Player },
+                damage: 2.0,
+                kind: DamageKind::Fire,
+                radius: 20.0,
+                falloff: 0.0,
+                knockback: 0.0,
+                effects: vec![],
+            },
+            Transform::default(),
+            HitTargets::default(),
+        )).id();
+
+        app.world.send_event(CollisionEvent::Started(explosion, enemy, CollisionEventFlags::SENSOR));
+        app.update();
+        app.world.send_event(CollisionEvent::Stopped(explosion, enemy, CollisionEventFlags::SENSOR));
+        app.world.send_event(CollisionEvent::Started(explosion, enemy, CollisionEventFlags::SENSOR));
+        app.update();
+
+        let damage_events = app.world.resource::<Events<DamageEvent>>();
+        assert_eq!(damage_events.get_reader().iter(damage_events).count(), 1);
+    }
+}
@@ -0,0 +1,110 @@
+use bevy::prelude::*;
+
+use crate::{GameState, GameResources, SPRITE_DRAW_SIZE, components::{FloatingText, TTL}};
+
+use super::{events::{DamageApplied, Healed}, damage::resolve_damage};
+
+const FLOATING_TEXT_Z_INDEX: f32 = 5.0;
+const FLOATING_TEXT_LIFETIME: f32 = 0.8;
+const RISE_SPEED: f32 = 40.0;
+const FONT_SIZE: f32 = 12.0;
+const CRITICAL_FONT_SIZE: f32 = 18.0;
+const DAMAGE_COLOR: Color = Color::WHITE;
+const CRITICAL_COLOR: Color = Color::rgb(1.0, 0.8, 0.2);
+const HEAL_COLOR: Color = Color::rgb(0.3, 0.95, 0.4);
+
+pub struct FloatingTextPlugin;
+
+impl Plugin for FloatingTextPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems((
+                spawn_damage_numbers.after(resolve_damage),
+                spawn_heal_numbers,
+                update_floating_text,
+            ).in_set(OnUpdate(GameState::Playing)));
+    }
+}
+
+/// Whole numbers without decimals, everything else with one.
+fn format_amount(amount: f32) -> String {
+    if amount.fract().abs() < 0.05 {
+        format!("{:.0}", amount)
+    } else {
+        format!("{:.1}", amount)
+    }
+}
+
+fn spawn_floating_text(
+    commands: &mut Commands,
+    game_resources: &GameResources,
+    position: Vec3,
+    text: String,
+    color: Color,
+    font_size: f32,
+) {
+    commands.spawn((
+        Text2dBundle {
+            text: Text::from_section(
+                text,
+                TextStyle {
+                    font: game_resources.font_handle.clone(),
+                    font_size,
+                    color,
+                },
+            ).with_alignment(TextAlignment::Center),
+            transform: Transform::from_xyz(position.x, position.y + SPRITE_DRAW_SIZE * 0.6, FLOATING_TEXT_Z_INDEX),
+            ..default()
+        },
+        FloatingText { rise_speed: RISE_SPEED },
+        TTL::new(FLOATING_TEXT_LIFETIME),
+    ));
+}
+
+fn spawn_damage_numbers(
+    mut commands: Commands,
+    game_resources: Res<GameResources>,
+    mut damage_applied: EventReader<DamageApplied>,
+    transform_q: Query<&GlobalTransform>,
+) {
+    for ev in damage_applied.iter() {
+        let Ok(transform) = transform_q.get(ev.target) else {
+            continue;
+        };
+        let (color, font_size) = if ev.critical {
+            (CRITICAL_COLOR, CRITICAL_FONT_SIZE)
+        } else {
+            (DAMAGE_COLOR, FONT_SIZE)
+        };
+        spawn_floating_text(&mut commands, &game_resources, transform.translation(), format_amount(ev.amount), color, font_size);
+    }
+}
+
+fn spawn_heal_numbers(
+    mut commands: Commands,
+    game_resources: Res<GameResources>,
+    mut healed: EventReader<Healed>,
+    transform_q: Query<&GlobalTransform>,
+) {
+    for ev in healed.iter() {
+        let Ok(transform) = transform_q.get(ev.target) else {
+            continue;
+        };
+        spawn_floating_text(&mut commands, &game_resources, transform.translation(), format!("+{}", format_amount(ev.amount)), HEAL_COLOR, FONT_SIZE);
+    }
+}
+
+
+fn update_floating_text(
+    time: Res<Time>,
+    mut text_q: Query<(&FloatingText, &TTL, &mut Transform, &mut Text)>,
+) {
+    let dt = time.delta_seconds();
+    for (floating_text, ttl, mut transform, mut text) in text_q.iter_mut() {
+        transform.translation.y += floating_text.rise_speed * dt;
+        let alpha = ttl.0.percent_left();
+        for section in text.sections.iter_mut() {
+            section.style.color.set_a(alpha);
+        }
+    }
+}
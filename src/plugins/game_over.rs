@@ -0,0 +1,42 @@
+use bevy::prelude::*;
+
+use crate::{GameState, GameResources, components::GameOverUi};
+
+use super::menu::{spawn_screen, despawn_with};
+
+pub struct GameOverPlugin;
+
+impl Plugin for GameOverPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_system(spawn_game_over_ui.in_schedule(OnEnter(GameState::GameOver)))
+            .add_system(game_over_input.in_set(OnUpdate(GameState::GameOver)))
+            .add_system(despawn_with::<GameOverUi>.in_schedule(OnExit(GameState::GameOver)));
+    }
+}
+
+fn spawn_game_over_ui(
+    mut commands: Commands,
+    game_resources: Res<GameResources>,
+) {
+    spawn_screen(
+        &mut commands,
+        &game_resources.font_handle,
+        GameOverUi,
+        Color::rgba(0.0, 0.0, 0.0, 0.6),
+        "GAME OVER",
+        Color::rgb(0.95, 0.25, 0.25),
+        &["press R to restart", "press Q to quit to menu"],
+    );
+}
+
+fn game_over_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::R) {
+        next_state.set(GameState::Playing);
+    } else if keyboard_input.just_pressed(KeyCode::Q) {
+        next_state.set(GameState::MainMenu);
+    }
+}
@@ -0,0 +1,392 @@
+use bevy::{prelude::*, ecs::schedule::common_conditions::{not, any_with_component}};
+
+use crate::{
+    GameState,
+    GameResources,
+    SPRITE_DRAW_SIZE,
+    SCALE_FACTOR,
+    components::{
+        Hud, HudSkillSlot, HudSkillIcon, HudCooldown, HudHealthFill, HudHealthText, HudWave, HudScore, HudCoins, HudPotions, HudLevel,
+        PlayerControlled, Inventory, Experience, SkillLoadout, Cooldowns, Health, HOTBAR_SLOTS, SECONDARY_SLOT,
+    },
+};
+
+use super::{progression::experience_for_level, menu::despawn_with, skills::SkillBook, animation::SpriteSheet, spawner::Spawner, score::Score};
+
+const ICON_SIZE: f32 = SPRITE_DRAW_SIZE;
+const SLOT_BORDER: f32 = 3.0;
+const SLOT_COLOR: Color = Color::rgb(0.3, 0.3, 0.35);
+const ACTIVE_SLOT_COLOR: Color = Color::rgb(0.95, 0.75, 0.25);
+const COOLDOWN_COLOR: Color = Color::rgba(0.0, 0.0, 0.0, 0.65);
+const HEALTH_BAR_WIDTH: f32 = 160.0;
+const HEALTH_BAR_HEIGHT: f32 = 12.0;
+const HEALTH_COLOR: Color = Color::rgb(0.95, 0.25, 0.25);
+const TEXT_SIZE: f32 = 10.0;
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_system(spawn_hud.run_if(not(any_with_component::<Hud>())).in_schedule(OnEnter(GameState::Playing)))
+            .add_systems((
+                update_skill_slots,
+                update_cooldown_overlays,
+                update_health,
+                update_wave,
+                update_score,
+                update_inventory,
+                update_level,
+            ).in_set(OnUpdate(GameState::Playing)))
+            .add_system(despawn_with::<Hud>.in_schedule(OnEnter(GameState::MainMenu)))
+            .add_system(despawn_with::<Hud>.in_schedule(OnExit(GameState::GameOver)));
+    }
+}
+
+fn slot_label(slot: usize) -> String {
+    if slot == SECONDARY_SLOT {
+        "RMB".to_string()
+    } else {
+        (slot + 1).to_string()
+    }
+}
+
+fn round_tenths(value: f32) -> f32 {
+    (value * 10.0).round() / 10.0
+}
+
+fn wave_label(wave: usize) -> String {
+    format!("WAVE {}", wave)
+}
+
+fn score_label(score: u32) -> String {
+    format!("SCORE {}", score)
+}
+
+fn coins_label(coins: u32) -> String {
+    format!("COINS {}", coins)
+}
+
+fn potions_label(potions: usize) -> String {
+    format!("POTIONS {} [Q]", potions)
+}
+
+fn level_label(experience: &Experience) -> String {
+    format!("LV {}  XP {}/{}", experience.level, experience.current, experience_for_level(experience.level))
+}
+
+fn spawn_hud(
+    mut commands: Commands,
+    game_resources: Res<GameResources>,
+    spawner: Res<Spawner>,
+    score: Res<Score>,
+) {
+    let text_style = TextStyle {
+        font: game_resources.font_handle.clone(),
+        font_size: TEXT_SIZE,
+        color: Color::WHITE,
+    };
+
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: Val::Px(8.0),
+                    ..default()
+                },
+                size: Size::width(Val::Percent(100.0)),
+                padding: UiRect::horizontal(Val::Px(8.0)),
+                justify_content: JustifyContent::SpaceBetween,
+                ..default()
+            },
+            ..default()
+        },
+        Hud,
+    ))
+    .with_children(|builder| {
+        builder.spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Column,
+                gap: Size::height(Val::Px(4.0)),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|builder| {
+            builder.spawn((TextBundle::from_section("", text_style.clone()), HudHealthText));
+            builder.spawn(NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Px(HEALTH_BAR_WIDTH), Val::Px(HEALTH_BAR_HEIGHT)),
+                    ..default()
+                },
+                background_color: SLOT_COLOR.into(),
+                ..default()
+            })
+            .with_children(|builder| {
+                builder.spawn((
+                    NodeBundle {
+                        style: Style {
+                            size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                            ..default()
+                        },
+                        background_color: HEALTH_COLOR.into(),
+                        ..default()
+                    },
+                    HudHealthFill,
+                ));
+            });
+            builder.spawn((TextBundle::from_section(level_label(&Experience::default()), text_style.clone()), HudLevel));
+            builder.spawn((TextBundle::from_section(coins_label(0), text_style.clone()), HudCoins));
+            builder.spawn((TextBundle::from_section(potions_label(0), text_style.clone()), HudPotions));
+        });
+        builder.spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::FlexEnd,
+                gap: Size::height(Val::Px(4.0)),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|builder| {
+            builder.spawn((TextBundle::from_section(wave_label(spawner.wave), text_style.clone()), HudWave));
+            builder.spawn((TextBundle::from_section(score_label(score.0), text_style.clone()), HudScore));
+        });
+    });
+
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    bottom: Val::Px(8.0),
+                    ..default()
+                },
+                size: Size::width(Val::Percent(100.0)),
+                justify_content: JustifyContent::Center,
+                gap: Size::width(Val::Px(8.0)),
+                ..default()
+            },
+            ..default()
+        },
+        Hud,
+    ))
+    .with_children(|builder| {
+        for slot in 0..=HOTBAR_SLOTS {
+            builder.spawn(NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    gap: Size::height(Val::Px(4.0)),
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|builder| {
+                builder.spawn((
+                    NodeBundle {
+                        style: Style {
+                            padding: UiRect::all(Val::Px(SLOT_BORDER)),
+                            ..default()
+                        },
+                        background_color: SLOT_COLOR.into(),
+                        ..default()
+                    },
+                    HudSkillSlot(slot),
+                ))
+                .with_children(|builder| {
+                    builder.spawn(NodeBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(ICON_SIZE), Val::Px(ICON_SIZE)),
+                            overflow: Overflow::Hidden,
+                            ..default()
+                        },
+                        background_color: Color::rgb(0.1, 0.1, 0.15).into(),
+                        ..default()
+                    })
+                    .with_children(|builder| {
+                        // the whole sheet is drawn and the slot clips everything but the skill's sprite
+                        builder.spawn((
+                            ImageBundle {
+                                style: Style {
+                                    position_type: PositionType::Absolute,
+                                    ..default()
+                                },
+                                image: game_resources.image_handle.clone().into(),
+                                visibility: Visibility::Hidden,
+                                ..default()
+                            },
+                            HudSkillIcon(slot),
+                        ));
+                        builder.spawn((
+                            NodeBundle {
+                                style: Style {
+                                    position_type: PositionType::Absolute,
+                                    position: UiRect {
+                                        left: Val::Px(0.0),
+                                        bottom: Val::Px(0.0),
+                                        ..default()
+                                    },
+                                    size: Size::new(Val::Percent(100.0), Val::Percent(0.0)),
+                                    ..default()
+                                },
+                                background_color: COOLDOWN_COLOR.into(),
+                                ..default()
+                            },
+                            HudCooldown(slot),
+                        ));
+                    });
+                });
+                builder.spawn(TextBundle::from_section(slot_label(slot), text_style.clone()));
+            });
+        }
+    });
+}
+
+fn update_skill_slots(
+    game_resources: Res<GameResources>,
+    skill_books: Res<Assets<SkillBook>>,
+    sprite_sheets: Res<Assets<SpriteSheet>>,
+    texture_atlases: Res<Assets<TextureAtlas>>,
+    player_q: Query<&SkillLoadout, With<PlayerControlled>>,
+    mut slot_q: Query<(&HudSkillSlot, &mut BackgroundColor)>,
+    mut icon_q: Query<(&HudSkillIcon, &mut Style, &mut Visibility)>,
+) {
+    let Ok(loadout) = player_q.get_single() else {
+        return;
+    };
+    let Some(skill_book) = skill_books.get(&game_resources.skills_handle) else {
+        return;
+    };
+    let Some((sprite_sheet, atlas)) = sprite_sheets.get(&game_resources.sheet_handle)
+        .and_then(|sprite_sheet| Some((sprite_sheet, texture_atlases.get(&sprite_sheet.atlas)?))) else
+    {
+        return;
+    };
+
+    for (slot, mut background_color) in slot_q.iter_mut() {
+        let color = if slot.0 == loadout.active { ACTIVE_SLOT_COLOR } else { SLOT_COLOR };
+        if background_color.0 != color {
+            background_color.0 = color;
+        }
+    }
+
+    for (icon, mut style, mut visibility) in icon_q.iter_mut() {
+        let sprite_rect = loadout.slots[icon.0].as_ref()
+            .and_then(|skill| skill_book.skills.get(skill))
+            .and_then(|skill| sprite_sheet.sprite(&skill.sprite))
+            .and_then(|index| atlas.textures.get(index));
+        let Some(sprite_rect) = sprite_rect else {
+            if *visibility != Visibility::Hidden {
+                *visibility = Visibility::Hidden;
+            }
+            continue;
+        };
+        // the whole sheet is scaled up and shifted so the sprite lands in the slot
+        let size = Size::new(Val::Px(atlas.size.x * SCALE_FACTOR), Val::Px(atlas.size.y * SCALE_FACTOR));
+        let position = UiRect {
+            left: Val::Px(-sprite_rect.min.x * SCALE_FACTOR),
+            top: Val::Px(-sprite_rect.min.y * SCALE_FACTOR),
+            ..default()
+        };
+        if style.size != size {
+            style.size = size;
+        }
+        if style.position != position {
+            style.position = position;
+        }
+        if *visibility != Visibility::Inherited {
+            *visibility = Visibility::Inherited;
+        }
+    }
+}
+
+fn update_cooldown_overlays(
+    player_q: Query<(&SkillLoadout, &Cooldowns), With<PlayerControlled>>,
+    mut overlay_q: Query<(&HudCooldown, &mut Style)>,
+) {
+    let Ok((loadout, cooldowns)) = player_q.get_single() else {
+        return;
+    };
+    for (overlay, mut style) in overlay_q.iter_mut() {
+        let remaining = loadout.slots[overlay.0].as_ref().map_or(0.0, |skill| cooldowns.remaining(skill));
+        let height = Val::Percent(remaining * 100.0);
+        if style.size.height != height {
+            style.size.height = height;
+        }
+    }
+}
+
+fn update_health(
+    player_q: Query<&Health, (With<PlayerControlled>, Changed<Health>)>,
+    mut fill_q: Query<&mut Style, With<HudHealthFill>>,
+    mut text_q: Query<&mut Text, With<HudHealthText>>,
+) {
+    let Ok(health) = player_q.get_single() else {
+        return;
+    };
+    let ratio = (health.act / health.max).clamp(0.0, 1.0);
+    for mut style in fill_q.iter_mut() {
+        style.size.width = Val::Percent(ratio * 100.0);
+    }
+    for mut text in text_q.iter_mut() {
+        text.sections[0].value = format!("HP {}/{}", round_tenths(health.act.max(0.0)), round_tenths(health.max));
+    }
+}
+
+/// Follows the spawner rather than `WaveStarted`, so a level change shows up right away.
+fn update_wave(
+    spawner: Res<Spawner>,
+    mut text_q: Query<&mut Text, With<HudWave>>,
+) {
+    if !spawner.is_changed() {
+        return;
+    }
+    let label = wave_label(spawner.wave);
+    for mut text in text_q.iter_mut() {
+        if text.sections[0].value != label {
+            text.sections[0].value = label.clone();
+        }
+    }
+}
+
+fn update_score(
+    score: Res<Score>,
+    mut text_q: Query<&mut Text, With<HudScore>>,
+) {
+    if !score.is_changed() {
+        return;
+    }
+    for mut text in text_q.iter_mut() {
+        text.sections[0].value = score_label(score.0);
+    }
+}
+
+fn update_inventory(
+    player_q: Query<&Inventory, (With<PlayerControlled>, Changed<Inventory>)>,
+    mut coins_q: Query<&mut Text, (With<HudCoins>, Without<HudPotions>)>,
+    mut potions_q: Query<&mut Text, (With<HudPotions>, Without<HudCoins>)>,
+) {
+    let Ok(inventory) = player_q.get_single() else {
+        return;
+    };
+    for mut text in coins_q.iter_mut() {
+        text.sections[0].value = coins_label(inventory.coins);
+    }
+    for mut text in potions_q.iter_mut() {
+        text.sections[0].value = potions_label(inventory.potions());
+    }
+}
+
+fn update_level(
+    player_q: Query<&Experience, (With<PlayerControlled>, Changed<Experience>)>,
+    mut text_q: Query<&mut Text, With<HudLevel>>,
+) {
+    let Ok(experience) = player_q.get_single() else {
+        return;
+    };
+    for mut text in text_q.iter_mut() {
+        text.sections[0].value = level_label(experience);
+    }
+}
@@ -0,0 +1,107 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::CollisionEvent;
+
+use crate::{GameState, components::{Item, Loot, Inventory, SkillLoadout, Health, PlayerControlled}};
+
+use super::events::Healed;
+
+const DRINK_KEY: KeyCode = KeyCode::Q;
+
+pub struct InventoryPlugin;
+
+impl Plugin for InventoryPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems((
+                collect_pickups,
+                drink_potion,
+            ).in_set(OnUpdate(GameState::Playing)));
+    }
+}
+
+/// Health restored by `heal` without going over the maximum.
+pub fn restore_health(health: &mut Health, heal: f32) -> f32 {
+    let restored = heal.min(health.max - health.act).max(0.0);
+    health.act += restored;
+    restored
+}
+
+/// Coins and potions go to the inventory, scrolls are learned on the spot.
+fn collect_pickups(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    loot_q: Query<&Loot>,
+    mut player_q: Query<(&mut Inventory, &mut SkillLoadout), With<PlayerControlled>>,
+) {
+    for collision_event in collision_events.iter() {
+        let CollisionEvent::Started(e1, e2, _) = collision_event else {
+            continue;
+        };
+        let (loot_e, other) = if loot_q.contains(*e1) { (*e1, *e2) } else { (*e2, *e1) };
+        let (Ok(loot), Ok((mut inventory, mut loadout))) = (loot_q.get(loot_e), player_q.get_mut(other)) else {
+            continue;
+        };
+        match &loot.0 {
+            Item::Coins(coins) => inventory.coins += coins,
+            Item::SkillScroll { skill } => {
+                let slot = loadout.learn(skill);
+                info!("learned {} in slot {}", skill, slot);
+            },
+            item => inventory.items.push(item.clone()),
+        }
+        commands.entity(loot_e).despawn_recursive();
+    }
+}
+
+fn drink_potion(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut healed: EventWriter<Healed>,
+    mut player_q: Query<(Entity, &mut Inventory, &mut Health), With<PlayerControlled>>,
+) {
+    if !keyboard_input.just_pressed(DRINK_KEY) {
+        return;
+    }
+    let Ok((entity, mut inventory, mut health)) = player_q.get_single_mut() else {
+        return;
+    };
+    if health.act >= health.max {
+        return;
+    }
+    let Some(heal) = inventory.take_potion() else {
+        return;
+    };
+    let amount = restore_health(&mut health, heal);
+    healed.send(Healed { target: entity, amount });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::components::{Health, Item, Inventory, SkillLoadout, SECONDARY_SLOT};
+
+    use super::restore_health;
+
+    #[test]
+    fn healing_stops_at_max_health() {
+        let mut health = Health { act: 2.5, max: 3.0 };
+        assert_eq!(restore_health(&mut health, 1.0), 0.5);
+        assert_eq!(health.act, 3.0);
+    }
+
+    #[test]
+    fn scrolls_unlock_free_slots_then_replace_the_secondary() {
+        let mut loadout = SkillLoadout::new(&["punch"]);
+        assert_eq!(loadout.learn("slash"), 1);
+        assert_eq!(loadout.learn("slash"), SECONDARY_SLOT);
+        assert_eq!(loadout.slots[SECONDARY_SLOT].as_deref(), Some("slash"));
+    }
+
+    #[test]
+    fn potions_are_taken_in_pickup_order() {
+        let mut inventory = Inventory {
+            coins: 0,
+            items: vec![Item::HealthPotion { heal: 1.0 }, Item::HealthPotion { heal: 2.0 }],
+        };
+        assert_eq!(inventory.take_potion(), Some(1.0));
+        assert_eq!(inventory.potions(), 1);
+    }
+}
@@ -0,0 +1,308 @@
+use bevy::{
+    prelude::*,
+    math::vec2,
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    reflect::TypeUuid,
+    utils::{BoxedFuture, HashMap},
+    ecs::schedule::common_conditions::{not, any_with_component},
+};
+use bevy_rapier2d::prelude::{RigidBody, Collider, ActiveCollisionTypes};
+use serde::Deserialize;
+
+use crate::{
+    GameState,
+    GameResources,
+    SPRITE_DRAW_SIZE,
+    CHARACTER_Z_INDEX,
+    spawn_player,
+    components::{Animation, Wall, Floor, EnemySpawnPoint, Character, Attack, Explosion, Particle, Loot, PlayerControlled, FloatingText},
+};
+
+use super::{ai::EnemyArchetype, events::ChangeLevel};
+
+const FLOOR_Z_INDEX: f32 = 0.0;
+const FLOOR_COLOR: Color = Color::rgb(0.12, 0.11, 0.14);
+
+/// Everything that belongs to the running level and goes away with it.
+type WorldEntities = Or<(With<Character>, With<Wall>, With<Floor>, With<EnemySpawnPoint>, With<Attack>, With<Explosion>, With<Particle>, With<Loot>, With<FloatingText>)>;
+/// The running level without the player, who carries over into the next one.
+type LevelEntities = (WorldEntities, Without<PlayerControlled>);
+
+pub struct LevelPlugin;
+
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_asset::<Level>()
+            .init_asset_loader::<LevelLoader>()
+            .init_resource::<CurrentLevel>()
+            .add_system(setup_level.run_if(not(any_with_component::<PlayerControlled>())).in_schedule(OnEnter(GameState::Playing)))
+            .add_systems((
+                next_level_on_input,
+                change_level.after(next_level_on_input),
+            ).in_set(OnUpdate(GameState::Playing)))
+            .add_system(teardown_world.in_schedule(OnEnter(GameState::MainMenu)))
+            .add_system(teardown_world.in_schedule(OnExit(GameState::GameOver)));
+    }
+}
+
+/// Index into `GameResources::levels` of the level being played.
+#[derive(Resource, Default)]
+pub struct CurrentLevel(pub usize);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Tile {
+    Wall,
+    Floor,
+    PlayerSpawn,
+    EnemySpawn(EnemyArchetype),
+}
+
+/// Level laid out as rows of characters: `#` is a wall, `.` floor, `P` the player spawn,
+/// enemy spawns use the characters listed in `enemies` and a space leaves the cell empty.
+#[derive(Deserialize, TypeUuid, Debug)]
+#[uuid = "9b3d1f62-2c47-4e8a-b5f0-7a1e4c9d8e23"]
+pub struct Level {
+    pub name: String,
+    #[serde(default)]
+    pub enemies: HashMap<char, EnemyArchetype>,
+    pub tiles: Vec<String>,
+}
+
+impl Level {
+    fn tile(&self, symbol: char) -> Option<Tile> {
+        match symbol {
+            '#' => Some(Tile::Wall),
+            '.' => Some(Tile::Floor),
+            'P' => Some(Tile::PlayerSpawn),
+            _ => self.enemies.get(&symbol).map(|archetype| Tile::EnemySpawn(*archetype)),
+        }
+    }
+
+    /// Every tile with its world position, the grid is centered on the origin.
+    pub fn tiles(&self) -> impl Iterator<Item = (Vec2, Tile)> + '_ {
+        let rows = self.tiles.len() as f32;
+        let columns = self.tiles.iter().map(|row| row.chars().count()).max().unwrap_or(0) as f32;
+        self.tiles.iter().enumerate().flat_map(move |(row, line)| {
+            line.chars().enumerate().filter_map(move |(column, symbol)| {
+                let position = vec2(
+                    (column as f32 - (columns - 1.0) / 2.0) * SPRITE_DRAW_SIZE,
+                    ((rows - 1.0) / 2.0 - row as f32) * SPRITE_DRAW_SIZE,
+                );
+                match self.tile(symbol) {
+                    Some(tile) => Some((position, tile)),
+                    None => {
+                        if symbol != ' ' {
+                            warn!("unknown tile '{}' in level {}", symbol, self.name);
+                        }
+                        None
+                    }
+                }
+            })
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct LevelLoader;
+
+impl AssetLoader for LevelLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let level = ron::de::from_bytes::<Level>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(level));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["level.ron"]
+    }
+}
+
+/// Builds the level and returns where the player starts in it.
+fn spawn_level(
+    commands: &mut Commands,
+    game_resources: &GameResources,
+    level: &Level,
+) -> Vec2 {
+    info!("building level {}", level.name);
+    let mut player_position = None;
+    for (position, tile) in level.tiles() {
+        match tile {
+            Tile::Wall => {
+                commands.spawn((
+                    RigidBody::Fixed,
+                    Collider::cuboid(SPRITE_DRAW_SIZE / 2.0, SPRITE_DRAW_SIZE / 2.0),
+                    SpriteSheetBundle {
+                        sprite: TextureAtlasSprite {
+                            custom_size: Some(vec2(SPRITE_DRAW_SIZE, SPRITE_DRAW_SIZE)),
+                            ..default()
+                        },
+                        texture_atlas: game_resources.atlas_handle.clone(),
+                        transform: Transform::from_xyz(position.x, position.y, CHARACTER_Z_INDEX),
+                        ..default()
+                    },
+                    Animation::new("wall"),
+                    ActiveCollisionTypes::all(),
+                    Wall,
+                ));
+                continue;
+            },
+            Tile::Floor => {},
+            Tile::PlayerSpawn => {
+                player_position = Some(position);
+            },
+            Tile::EnemySpawn(archetype) => {
+                commands.spawn((
+                    EnemySpawnPoint { archetype },
+                    TransformBundle::from_transform(Transform::from_xyz(position.x, position.y, 0.0)),
+                ));
+            },
+        }
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: FLOOR_COLOR,
+                    custom_size: Some(vec2(SPRITE_DRAW_SIZE, SPRITE_DRAW_SIZE)),
+                    ..default()
+                },
+                transform: Transform::from_xyz(position.x, position.y, FLOOR_Z_INDEX),
+                ..default()
+            },
+            Floor,
+        ));
+    }
+
+    player_position.unwrap_or_else(|| {
+        warn!("level {} has no player spawn", level.name);
+        Vec2::ZERO
+    })
+}
+
+fn setup_level(
+    mut commands: Commands,
+    game_resources: Res<GameResources>,
+    levels: Res<Assets<Level>>,
+    current_level: Res<CurrentLevel>,
+) {
+    let Some(level) = game_resources.levels.get(current_level.0).and_then(|handle| levels.get(handle)) else {
+        warn!("level {} is not loaded", current_level.0);
+        return;
+    };
+    let player_position = spawn_level(&mut commands, &game_resources, level);
+    spawn_player(&mut commands, &game_resources, player_position);
+}
+
+
+fn next_level_on_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    game_resources: Res<GameResources>,
+    current_level: Res<CurrentLevel>,
+    mut change_level_events: EventWriter<ChangeLevel>,
+) {
+    if keyboard_input.just_pressed(KeyCode::N) && !game_resources.levels.is_empty() {
+        change_level_events.send(ChangeLevel((current_level.0 + 1) % game_resources.levels.len()));
+    }
+}
+
+pub fn change_level(
+    mut commands: Commands,
+    mut change_level_events: EventReader<ChangeLevel>,
+    game_resources: Res<GameResources>,
+    levels: Res<Assets<Level>>,
+    mut current_level: ResMut<CurrentLevel>,
+    world_q: Query<Entity, LevelEntities>,
+    mut player_q: Query<&mut Transform, With<PlayerControlled>>,
+) {
+    let Some(ChangeLevel(index)) = change_level_events.iter().last() else {
+        return;
+    };
+    let Some(level) = game_resources.levels.get(*index).and_then(|handle| levels.get(handle)) else {
+        warn!("level {} is not loaded", index);
+        return;
+    };
+    for entity in world_q.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    current_level.0 = *index;
+    let player_position = spawn_level(&mut commands, &game_resources, level);
+    for mut transform in player_q.iter_mut() {
+        transform.translation.x = player_position.x;
+        transform.translation.y = player_position.y;
+    }
+}
+
+fn teardown_world(
+    mut commands: Commands,
+    world_q: Query<Entity, WorldEntities>,
+) {
+    for entity in world_q.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{prelude::*, utils::HashMap};
+
+    use crate::{
+        GameResources,
+        components::{Character, PlayerControlled, Inventory, SkillLoadout, Experience, Wall},
+        plugins::events::ChangeLevel,
+    };
+
+    use super::{Level, CurrentLevel, change_level};
+
+    #[test]
+    fn player_keeps_inventory_skills_and_experience_across_levels() {
+        let mut app = App::new();
+        app
+            .add_plugins(MinimalPlugins)
+            .add_plugin(AssetPlugin::default())
+            .add_asset::<Level>()
+            .add_event::<ChangeLevel>()
+            .init_resource::<CurrentLevel>()
+            .add_system(change_level);
+        let level = app.world.resource_mut::<Assets<Level>>().add(Level {
+            name: "test".to_string(),
+            enemies: HashMap::default(),
+            tiles: vec!["#P.".to_string()],
+        });
+        app.insert_resource(GameResources {
+            image_handle: default(),
+            font_handle: default(),
+            atlas_handle: default(),
+            skills_handle: default(),
+            sheet_handle: default(),
+            waves_handle: default(),
+            particles_handle: default(),
+            levels: vec![level],
+        });
+        let skills = SkillLoadout::new(&["punch"]).with_secondary("fireball");
+        let slots = skills.slots.clone();
+        let player = app.world.spawn((
+            Character,
+            PlayerControlled,
+            Transform::from_xyz(100.0, 100.0, 0.0),
+            Inventory { coins: 7, items: vec![] },
+            skills,
+            Experience { level: 3, current: 5, unspent: 1 },
+        )).id();
+        let old_wall = app.world.spawn(Wall).id();
+
+        app.world.send_event(ChangeLevel(0));
+        app.update();
+
+        assert!(app.world.get_entity(old_wall).is_none());
+        let player = app.world.entity(player);
+        assert_eq!(player.get::<Inventory>().unwrap().coins, 7);
+        assert_eq!(player.get::<SkillLoadout>().unwrap().slots, slots);
+        assert_eq!(player.get::<Experience>().unwrap().level, 3);
+        assert_eq!(player.get::<Transform>().unwrap().translation.truncate(), Vec2::ZERO);
+    }
+}
@@ -0,0 +1,41 @@
+use bevy::{prelude::*, asset::LoadState};
+
+use crate::{GameState, GameResources, components::LoadingUi};
+
+use super::menu::{spawn_screen, despawn_with};
+
+pub struct LoadingPlugin;
+
+impl Plugin for LoadingPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_system(spawn_loading_ui.in_schedule(OnEnter(GameState::Loading)))
+            .add_system(check_assets_loaded.in_set(OnUpdate(GameState::Loading)))
+            .add_system(despawn_with::<LoadingUi>.in_schedule(OnExit(GameState::Loading)));
+    }
+}
+
+fn spawn_loading_ui(
+    mut commands: Commands,
+    game_resources: Res<GameResources>,
+) {
+    spawn_screen(
+        &mut commands,
+        &game_resources.font_handle,
+        LoadingUi,
+        Color::rgb(0.1, 0.1, 0.15),
+        "LOADING",
+        Color::WHITE,
+        &[],
+    );
+}
+
+fn check_assets_loaded(
+    asset_server: Res<AssetServer>,
+    game_resources: Res<GameResources>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if game_resources.load_state(&asset_server) == LoadState::Loaded {
+        next_state.set(GameState::MainMenu);
+    }
+}
@@ -0,0 +1,102 @@
+use bevy::{prelude::*, math::vec2};
+use bevy_rapier2d::prelude::{Collider, Sensor, ActiveEvents, ActiveCollisionTypes};
+use rand::Rng;
+
+use crate::{
+    GameState,
+    GameResources,
+    SPRITE_DRAW_SIZE,
+    components::{Item, Loot, LootTable, Animation, TTL},
+};
+
+use super::{events::Died, damage::resolve_damage, rng::GameRng};
+
+const LOOT_Z_INDEX: f32 = 0.5;
+/// Seconds loot stays on the ground.
+const LOOT_LIFETIME: f32 = 30.0;
+/// Items are scattered up to this far from where the character died.
+const LOOT_SCATTER: f32 = SPRITE_DRAW_SIZE * 0.5;
+
+pub struct LootPlugin;
+
+impl Plugin for LootPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_system(drop_loot.after(resolve_damage).in_set(OnUpdate(GameState::Playing)));
+    }
+}
+
+/// Every drop of the table is rolled on its own.
+pub fn roll_loot<R: Rng>(rng: &mut R, table: &LootTable) -> Vec<Item> {
+    table.0.iter()
+        .filter(|drop| rng.gen_bool(drop.chance.clamp(0.0, 1.0) as f64))
+        .map(|drop| drop.item.clone())
+        .collect()
+}
+
+pub fn spawn_loot(
+    commands: &mut Commands,
+    game_resources: &GameResources,
+    item: Item,
+    position: Vec2,
+) -> Entity {
+    commands.spawn((
+        SpriteSheetBundle {
+            sprite: TextureAtlasSprite {
+                custom_size: Some(vec2(SPRITE_DRAW_SIZE, SPRITE_DRAW_SIZE)),
+                ..default()
+            },
+            texture_atlas: game_resources.atlas_handle.clone(),
+            transform: Transform::from_xyz(position.x, position.y, LOOT_Z_INDEX),
+            ..default()
+        },
+        Animation::new(item.sprite_name()),
+        Loot(item),
+        TTL::new(LOOT_LIFETIME),
+        Sensor,
+        Collider::ball(SPRITE_DRAW_SIZE * 0.3),
+        ActiveEvents::COLLISION_EVENTS,
+        ActiveCollisionTypes::default() | ActiveCollisionTypes::KINEMATIC_STATIC,
+    ))
+    .id()
+}
+
+fn drop_loot(
+    mut commands: Commands,
+    mut rng: ResMut<GameRng>,
+    game_resources: Res<GameResources>,
+    mut died_events: EventReader<Died>,
+    loot_q: Query<(&Transform, &LootTable)>,
+) {
+    for ev in died_events.iter() {
+        let Ok((transform, table)) = loot_q.get(ev.entity) else {
+            continue;
+        };
+        for item in roll_loot(&mut *rng, table) {
+            let offset = vec2(rng.gen_range(-1.0..=1.0), rng.gen_range(-1.0..=1.0)) * LOOT_SCATTER;
+            spawn_loot(&mut commands, &game_resources, item, transform.translation.truncate() + offset);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        components::{Item, LootDrop, LootTable},
+        plugins::rng::GameRng,
+    };
+
+    use super::roll_loot;
+
+    #[test]
+    fn certain_drops_always_drop_and_impossible_never() {
+        let table = LootTable(vec![
+            LootDrop { item: Item::Coins(3), chance: 1.0 },
+            LootDrop { item: Item::HealthPotion { heal: 1.0 }, chance: 0.0 },
+        ]);
+        let mut rng = GameRng::from_seed(0);
+        for _ in 0..20 {
+            assert_eq!(roll_loot(&mut rng, &table), vec![Item::Coins(3)]);
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use bevy::prelude::*;
+
+use crate::{GameState, GameResources, components::{MainMenuUi, PauseUi}};
+
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_system(spawn_main_menu_ui.in_schedule(OnEnter(GameState::MainMenu)))
+            .add_system(main_menu_input.in_set(OnUpdate(GameState::MainMenu)))
+            .add_system(despawn_with::<MainMenuUi>.in_schedule(OnExit(GameState::MainMenu)))
+            .add_system(pause_on_input.in_set(OnUpdate(GameState::Playing)))
+            .add_system(spawn_pause_ui.in_schedule(OnEnter(GameState::Paused)))
+            .add_system(pause_menu_input.in_set(OnUpdate(GameState::Paused)))
+            .add_system(despawn_with::<PauseUi>.in_schedule(OnExit(GameState::Paused)));
+    }
+}
+
+/// Spawns a full screen overlay with a big title and a few lines of hints underneath.
+pub fn spawn_screen<T: Component>(
+    commands: &mut Commands,
+    font: &Handle<Font>,
+    marker: T,
+    background: Color,
+    title: &str,
+    title_color: Color,
+    lines: &[&str],
+) {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                gap: Size::height(Val::Px(24.0)),
+                ..default()
+            },
+            background_color: background.into(),
+            ..default()
+        },
+        marker,
+    ))
+    .with_children(|builder| {
+        builder.spawn(TextBundle::from_section(
+            title,
+            TextStyle {
+                font: font.clone(),
+                font_size: 40.0,
+                color: title_color,
+            },
+        ));
+        for line in lines {
+            builder.spawn(TextBundle::from_section(
+                *line,
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 16.0,
+                    color: Color::WHITE,
+                },
+            ));
+        }
+    });
+}
+
+pub fn despawn_with<T: Component>(
+    mut commands: Commands,
+    q: Query<Entity, With<T>>,
+) {
+    for entity in q.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn spawn_main_menu_ui(
+    mut commands: Commands,
+    game_resources: Res<GameResources>,
+) {
+    spawn_screen(
+        &mut commands,
+        &game_resources.font_handle,
+        MainMenuUi,
+        Color::rgb(0.1, 0.1, 0.15),
+        "ROTARY PHONE",
+        Color::rgb(0.95, 0.75, 0.25),
+        &["press ENTER to start"],
+    );
+}
+
+fn main_menu_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Return) {
+        next_state.set(GameState::Playing);
+    }
+}
+
+fn pause_on_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        next_state.set(GameState::Paused);
+    }
+}
+
+fn spawn_pause_ui(
+    mut commands: Commands,
+    game_resources: Res<GameResources>,
+) {
+    spawn_screen(
+        &mut commands,
+        &game_resources.font_handle,
+        PauseUi,
+        Color::rgba(0.0, 0.0, 0.0, 0.6),
+        "PAUSED",
+        Color::WHITE,
+        &["press ESC to resume", "press Q to quit to menu"],
+    );
+}
+
+fn pause_menu_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        next_state.set(GameState::Playing);
+    } else if keyboard_input.just_pressed(KeyCode::Q) {
+        next_state.set(GameState::MainMenu);
+    }
+}
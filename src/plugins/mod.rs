@@ -3,4 +3,23 @@ pub mod collision;
 pub mod events;
 pub mod skills;
 pub mod character;
-pub mod ai;
\ No newline at end of file
+pub mod ai;
+pub mod game_over;
+pub mod menu;
+pub mod loading;
+pub mod hud;
+pub mod rng;
+pub mod level;
+pub mod animation;
+pub mod aseprite;
+pub mod spawner;
+pub mod damage;
+pub mod floating_text;
+pub mod score;
+pub mod status;
+pub mod explosion;
+pub mod particles;
+pub mod loot;
+pub mod inventory;
+pub mod progression;
+pub mod save;
\ No newline at end of file
@@ -0,0 +1,294 @@
+use bevy::{
+    prelude::*,
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    reflect::TypeUuid,
+    utils::{BoxedFuture, HashMap},
+};
+use bevy_rapier2d::prelude::Velocity;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::{GameState, GameResources, components::{Particle, ParticleTrail, TTL}};
+
+use super::{events::SpawnParticles, rng::CosmeticRng};
+
+pub const SPARKS: &str = "sparks";
+pub const DUST: &str = "dust";
+pub const DEATH_BURST: &str = "death_burst";
+
+/// Upper bound of live particles, emitters stay quiet while it is reached.
+const MAX_PARTICLES: usize = 300;
+const PARTICLE_Z_INDEX: f32 = 1.8;
+
+pub struct ParticlesPlugin;
+
+impl Plugin for ParticlesPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_asset::<ParticleBook>()
+            .init_asset_loader::<ParticleBookLoader>()
+            .init_resource::<ParticleBudget>()
+            .add_systems((
+                refill_particle_budget,
+                emit_bursts,
+                emit_trails,
+            ).chain().in_set(OnUpdate(GameState::Playing)))
+            .add_system(update_particles.in_set(OnUpdate(GameState::Playing)));
+    }
+}
+
+/// Particles the emitters may still spawn this frame, shared so bursts and trails together stay under `MAX_PARTICLES`.
+#[derive(Resource, Default)]
+pub struct ParticleBudget(usize);
+
+/// Ranges are `(min, max)` pairs a value is picked from for every particle.
+#[derive(Deserialize, Clone, Debug)]
+pub struct EmitterDefinition {
+    /// Particles spawned at once by a burst.
+    #[serde(default)]
+    pub burst: u32,
+    /// Particles per second emitted by a trail.
+    #[serde(default)]
+    pub rate: f32,
+    /// Seconds.
+    pub lifetime: (f32, f32),
+    /// Pixels per second.
+    pub speed: (f32, f32),
+    /// Angle around the emit direction particles fly within, in radians. Bursts without a direction fly all around.
+    pub spread: f32,
+    /// Share of the velocity lost every second.
+    #[serde(default)]
+    pub drag: f32,
+    /// Size at spawn and at the end of the lifetime, in pixels.
+    pub size: (f32, f32),
+    pub color: [f32; 4],
+    pub end_color: [f32; 4],
+}
+
+#[derive(Deserialize, TypeUuid, Debug)]
+#[uuid = "a3d5c8f1-2b7e-4c96-8e0a-5f4b1d7c9e23"]
+pub struct ParticleBook {
+    pub emitters: HashMap<String, EmitterDefinition>,
+}
+
+#[derive(Default)]
+pub struct ParticleBookLoader;
+
+impl AssetLoader for ParticleBookLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let particle_book = ron::de::from_bytes::<ParticleBook>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(particle_book));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["particles.ron"]
+    }
+}
+
+fn color(rgba: [f32; 4]) -> Color {
+    Color::rgba(rgba[0], rgba[1], rgba[2], rgba[3])
+}
+
+fn pick(rng: &mut impl Rng, (min, max): (f32, f32)) -> f32 {
+    if max > min {
+        rng.gen_range(min..max)
+    } else {
+        min
+    }
+}
+
+/// Spawns up to `count` particles flying around `direction`, returns how many were spawned.
+fn spawn_particles(
+    commands: &mut Commands,
+    rng: &mut impl Rng,
+    emitter: &EmitterDefinition,
+    position: Vec2,
+    direction: Vec2,
+    count: usize,
+) -> usize {
+    let direction = direction.normalize_or_zero();
+    for _ in 0..count {
+        let heading = if direction == Vec2::ZERO {
+            Vec2::from_angle(rng.gen_range(0.0..std::f32::consts::TAU))
+        } else {
+            let offset = emitter.spread * rng.gen_range(-1.0..=1.0);
+            Vec2::from_angle(offset).rotate(direction)
+        };
+        let size = emitter.size.0;
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: color(emitter.color),
+                    custom_size: Some(Vec2::splat(size)),
+                    ..default()
+                },
+                transform: Transform::from_translation(position.extend(PARTICLE_Z_INDEX)),
+                ..default()
+            },
+            Particle {
+                velocity: heading * pick(rng, emitter.speed),
+                drag: emitter.drag,
+                size: emitter.size,
+                color: color(emitter.color),
+                end_color: color(emitter.end_color),
+            },
+            TTL::new(pick(rng, emitter.lifetime)),
+        ));
+    }
+    count
+}
+
+/// Spawned particles only show up in queries once commands are applied, so the budget is counted once before the emitters run.
+fn refill_particle_budget(
+    mut budget: ResMut<ParticleBudget>,
+    particle_q: Query<(), With<Particle>>,
+) {
+    budget.0 = MAX_PARTICLES.saturating_sub(particle_q.iter().len());
+}
+
+fn emit_bursts(
+    mut commands: Commands,
+    game_resources: Res<GameResources>,
+    particle_books: Res<Assets<ParticleBook>>,
+    mut rng: ResMut<CosmeticRng>,
+    mut budget: ResMut<ParticleBudget>,
+    mut particle_events: EventReader<SpawnParticles>,
+) {
+    let Some(particle_book) = particle_books.get(&game_resources.particles_handle) else {
+        return;
+    };
+    for ev in particle_events.iter() {
+        let Some(emitter) = particle_book.emitters.get(&ev.emitter) else {
+            warn!("unknown particle emitter {}", ev.emitter);
+            continue;
+        };
+        budget.0 -= spawn_particles(&mut commands, &mut rng.0, emitter, ev.position, ev.direction, budget.0.min(emitter.burst as usize));
+    }
+}
+
+/// Trails leave particles behind, opposite to the direction their entity moves in.
+fn emit_trails(
+    time: Res<Time>,
+    mut commands: Commands,
+    game_resources: Res<GameResources>,
+    particle_books: Res<Assets<ParticleBook>>,
+    mut rng: ResMut<CosmeticRng>,
+    mut budget: ResMut<ParticleBudget>,
+    mut trail_q: Query<(&mut ParticleTrail, &GlobalTransform, Option<&Velocity>)>,
+) {
+    let Some(particle_book) = particle_books.get(&game_resources.particles_handle) else {
+        return;
+    };
+    let dt = time.delta_seconds();
+    for (mut trail, transform, velocity) in trail_q.iter_mut() {
+        let Some(emitter) = particle_book.emitters.get(&trail.emitter) else {
+            continue;
+        };
+        trail.pending += emitter.rate * dt;
+        let count = trail.pending as usize;
+        trail.pending -= count as f32;
+        let direction = velocity.map_or(Vec2::ZERO, |velocity| -velocity.linvel);
+        budget.0 -= spawn_particles(&mut commands, &mut rng.0, emitter, transform.translation().truncate(), direction, budget.0.min(count));
+    }
+}
+
+fn update_particles(
+    time: Res<Time>,
+    mut particle_q: Query<(&mut Particle, &TTL, &mut Transform, &mut Sprite)>,
+) {
+    let dt = time.delta_seconds();
+    for (mut particle, ttl, mut transform, mut sprite) in particle_q.iter_mut() {
+        let drag = (1.0 - particle.drag * dt).max(0.0);
+        particle.velocity *= drag;
+        transform.translation += (particle.velocity * dt).extend(0.0);
+
+        let progress = ttl.0.percent();
+        let size = particle.size.0 + (particle.size.1 - particle.size.0) * progress;
+        sprite.custom_size = Some(Vec2::splat(size));
+        let start = particle.color.as_rgba_f32();
+        let end = particle.end_color.as_rgba_f32();
+        sprite.color = Color::rgba(
+            start[0] + (end[0] - start[0]) * progress,
+            start[1] + (end[1] - start[1]) * progress,
+            start[2] + (end[2] - start[2]) * progress,
+            start[3] + (end[3] - start[3]) * progress,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{prelude::*, utils::HashMap};
+
+    use crate::{
+        GameResources,
+        components::{Particle, ParticleTrail},
+        plugins::{events::SpawnParticles, rng::{CosmeticRng, GameRng}},
+    };
+
+    use super::{EmitterDefinition, ParticleBook, ParticleBudget, MAX_PARTICLES, refill_particle_budget, emit_bursts, emit_trails};
+
+    fn emitter(burst: u32) -> EmitterDefinition {
+        EmitterDefinition {
+            burst,
+            rate: 0.0,
+            lifetime: (10.0, 10.0),
+            speed: (0.0, 0.0),
+            spread: 0.0,
+            drag: 0.0,
+            size: (1.0, 1.0),
+            color: [1.0; 4],
+            end_color: [1.0; 4],
+        }
+    }
+
+    #[test]
+    fn bursts_and_trails_share_the_particle_cap() {
+        let mut app = App::new();
+        app
+            .add_plugins(MinimalPlugins)
+            .add_plugin(AssetPlugin::default())
+            .add_asset::<ParticleBook>()
+            .add_event::<SpawnParticles>()
+            .insert_resource(CosmeticRng(GameRng::from_seed(0)))
+            .init_resource::<ParticleBudget>()
+            .add_systems((refill_particle_budget, emit_bursts, emit_trails).chain());
+        let particles_handle = app.world.resource_mut::<Assets<ParticleBook>>().add(ParticleBook {
+            emitters: HashMap::from_iter([
+                ("burst".to_string(), emitter(50)),
+                ("trail".to_string(), emitter(0)),
+            ]),
+        });
+        app.insert_resource(GameResources {
+            image_handle: default(),
+            font_handle: default(),
+            atlas_handle: default(),
+            skills_handle: default(),
+            sheet_handle: default(),
+            waves_handle: default(),
+            particles_handle,
+            levels: vec![],
+        });
+        for _ in 0..4 {
+            app.world.spawn((ParticleTrail { emitter: "trail".to_string(), pending: 100.0 }, GlobalTransform::default()));
+        }
+
+        for _ in 0..3 {
+            for _ in 0..8 {
+                app.world.send_event(SpawnParticles { emitter: "burst".to_string(), position: Vec2::ZERO, direction: Vec2::X });
+            }
+            app.update();
+            let mut particle_q = app.world.query_filtered::<(), With<Particle>>();
+            assert!(particle_q.iter(&app.world).count() <= MAX_PARTICLES);
+        }
+        let mut particle_q = app.world.query_filtered::<(), With<Particle>>();
+        assert_eq!(particle_q.iter(&app.world).count(), MAX_PARTICLES);
+    }
+}
@@ -0,0 +1,193 @@
+use bevy::prelude::*;
+use rand::seq::SliceRandom;
+
+use crate::{GameState, GameResources, components::{Stats, Experience, Health, PlayerControlled, LevelUpUi}};
+
+use super::{events::{Died, Healed}, damage::resolve_damage, menu::{spawn_screen, despawn_with}, rng::GameRng};
+
+const KILL_EXPERIENCE: u32 = 10;
+/// Upgrades offered on every level-up.
+const UPGRADE_CHOICES: usize = 3;
+const CHOICE_KEYS: [KeyCode; UPGRADE_CHOICES] = [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3];
+const MAX_COOLDOWN_REDUCTION: f32 = 0.5;
+
+pub struct ProgressionPlugin;
+
+impl Plugin for ProgressionPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<UpgradeChoices>()
+            .add_systems((
+                grant_experience.after(resolve_damage),
+                open_level_up.after(grant_experience),
+                apply_max_health,
+            ).in_set(OnUpdate(GameState::Playing)))
+            .add_system(spawn_level_up_ui.in_schedule(OnEnter(GameState::LevelUp)))
+            .add_system(pick_upgrade.in_set(OnUpdate(GameState::LevelUp)))
+            .add_system(despawn_with::<LevelUpUi>.in_schedule(OnExit(GameState::LevelUp)));
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Upgrade {
+    Strength,
+    Speed,
+    MaxHealth,
+    CooldownReduction,
+}
+
+impl Upgrade {
+    pub const ALL: [Upgrade; 4] = [Upgrade::Strength, Upgrade::Speed, Upgrade::MaxHealth, Upgrade::CooldownReduction];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Upgrade::Strength => "+20% damage",
+            Upgrade::Speed => "+10% movement speed",
+            Upgrade::MaxHealth => "+1 max health",
+            Upgrade::CooldownReduction => "-10% cooldowns",
+        }
+    }
+
+    pub fn apply(&self, stats: &mut Stats) {
+        match self {
+            Upgrade::Strength => stats.strength += 0.2,
+            Upgrade::Speed => stats.speed += 0.1,
+            Upgrade::MaxHealth => stats.max_health += 1.0,
+            Upgrade::CooldownReduction => {
+                stats.cooldown_reduction = (stats.cooldown_reduction + 0.1).min(MAX_COOLDOWN_REDUCTION);
+            },
+        }
+    }
+}
+
+/// Upgrades offered by the level-up screen that is open.
+#[derive(Resource, Default)]
+pub struct UpgradeChoices(pub Vec<Upgrade>);
+
+/// Experience needed to get from `level` to the next one.
+pub fn experience_for_level(level: u32) -> u32 {
+    20 + 10 * level
+}
+
+/// Adds experience and turns every full level of it into an unspent level-up.
+pub fn add_experience(experience: &mut Experience, amount: u32) {
+    experience.current += amount;
+    while experience.current >= experience_for_level(experience.level) {
+        experience.current -= experience_for_level(experience.level);
+        experience.level += 1;
+        experience.unspent += 1;
+    }
+}
+
+fn grant_experience(
+    mut died_events: EventReader<Died>,
+    mut experience_q: Query<&mut Experience>,
+) {
+    for ev in died_events.iter() {
+        if let Ok(mut experience) = experience_q.get_mut(ev.killer) {
+            add_experience(&mut experience, KILL_EXPERIENCE);
+        }
+    }
+}
+
+fn open_level_up(
+    mut next_state: ResMut<NextState<GameState>>,
+    player_q: Query<(&Experience, &Health), With<PlayerControlled>>,
+) {
+    let Ok((experience, health)) = player_q.get_single() else {
+        return;
+    };
+    // the game over screen wins over a level-up earned with the last breath
+    if experience.unspent > 0 && health.act > 0.0 {
+        next_state.set(GameState::LevelUp);
+    }
+}
+
+/// Raising the max health heals by the same amount.
+fn apply_max_health(
+    mut healed: EventWriter<Healed>,
+    mut stats_q: Query<(Entity, &Stats, &mut Health), Changed<Stats>>,
+) {
+    for (entity, stats, mut health) in stats_q.iter_mut() {
+        let gain = stats.max_health - health.max;
+        if gain == 0.0 {
+            continue;
+        }
+        health.max = stats.max_health;
+        let before = health.act;
+        health.act = (health.act + gain.max(0.0)).min(health.max);
+        if health.act > before {
+            healed.send(Healed { target: entity, amount: health.act - before });
+        }
+    }
+}
+
+fn spawn_level_up_ui(
+    mut commands: Commands,
+    mut rng: ResMut<GameRng>,
+    mut choices: ResMut<UpgradeChoices>,
+    game_resources: Res<GameResources>,
+    player_q: Query<&Experience, With<PlayerControlled>>,
+) {
+    choices.0 = Upgrade::ALL.choose_multiple(&mut *rng, UPGRADE_CHOICES).copied().collect();
+    let level = player_q.get_single().map_or(1, |experience| experience.level);
+    let lines: Vec<String> = choices.0.iter()
+        .enumerate()
+        .map(|(index, upgrade)| format!("{}  {}", index + 1, upgrade.label()))
+        .collect();
+    let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+    spawn_screen(
+        &mut commands,
+        &game_resources.font_handle,
+        LevelUpUi,
+        Color::rgba(0.0, 0.0, 0.0, 0.6),
+        &format!("LEVEL {}", level),
+        Color::rgb(0.95, 0.75, 0.25),
+        &lines,
+    );
+}
+
+fn pick_upgrade(
+    keyboard_input: Res<Input<KeyCode>>,
+    choices: Res<UpgradeChoices>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut player_q: Query<(&mut Stats, &mut Experience), With<PlayerControlled>>,
+) {
+    let Some(upgrade) = CHOICE_KEYS.iter()
+        .zip(choices.0.iter())
+        .find(|(key, _)| keyboard_input.just_pressed(**key))
+        .map(|(_, upgrade)| *upgrade) else
+    {
+        return;
+    };
+    if let Ok((mut stats, mut experience)) = player_q.get_single_mut() {
+        upgrade.apply(&mut stats);
+        experience.unspent = experience.unspent.saturating_sub(1);
+    }
+    next_state.set(GameState::Playing);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::components::{Experience, Stats};
+
+    use super::{add_experience, experience_for_level, Upgrade, MAX_COOLDOWN_REDUCTION};
+
+    #[test]
+    fn leftover_experience_carries_over_several_levels() {
+        let mut experience = Experience::default();
+        add_experience(&mut experience, experience_for_level(1) + experience_for_level(2) + 5);
+        assert_eq!(experience.level, 3);
+        assert_eq!(experience.unspent, 2);
+        assert_eq!(experience.current, 5);
+    }
+
+    #[test]
+    fn cooldown_reduction_is_capped() {
+        let mut stats = Stats::new(5.0);
+        for _ in 0..10 {
+            Upgrade::CooldownReduction.apply(&mut stats);
+        }
+        assert_eq!(stats.cooldown_reduction, MAX_COOLDOWN_REDUCTION);
+    }
+}
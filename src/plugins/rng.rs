@@ -0,0 +1,71 @@
+use bevy::prelude::*;
+use rand::{RngCore, SeedableRng, rngs::StdRng};
+
+/// Mixed into the seed of the cosmetic stream so it does not repeat the gameplay one.
+const COSMETIC_STREAM: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// Seeds the `GameRng`, a random seed is picked and logged when none is given.
+pub struct RngPlugin {
+    pub seed: Option<u64>,
+}
+
+impl Plugin for RngPlugin {
+    fn build(&self, app: &mut App) {
+        let seed = self.seed.unwrap_or_else(rand::random);
+        info!("game seed: {}", seed);
+        app
+            .insert_resource(GameRng::from_seed(seed))
+            .insert_resource(CosmeticRng(GameRng::from_seed(seed ^ COSMETIC_STREAM)));
+    }
+}
+
+/// Reads the seed passed as `--seed <number>` on the command line.
+pub fn seed_from_args() -> Option<u64> {
+    let mut args = std::env::args();
+    while let Some(arg) = args.next() {
+        if arg == "--seed" {
+            return args.next().and_then(|seed| seed.parse().ok());
+        }
+    }
+    None
+}
+
+/// Source of randomness for every gameplay system, so a session can be reproduced from its seed.
+#[derive(Resource)]
+pub struct GameRng {
+    seed: u64,
+    rng: StdRng,
+}
+
+impl GameRng {
+    pub fn from_seed(seed: u64) -> Self {
+        Self { seed, rng: StdRng::seed_from_u64(seed) }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+/// Second stream seeded from the same seed, for effects like particles whose draws depend on the frame rate
+/// and must not shift the `GameRng` sequence.
+#[derive(Resource)]
+pub struct CosmeticRng(pub GameRng);
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
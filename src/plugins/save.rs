@@ -0,0 +1,302 @@
+use std::fmt;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{GameState, LEVELS, components::{PlayerControlled, Health, Stats, Experience, SkillLoadout, Inventory}};
+
+use super::{
+    events::ChangeLevel,
+    level::{CurrentLevel, change_level},
+    spawner::{Spawner, reset_spawner},
+    score::Score,
+};
+
+const SAVE_KEY: KeyCode = KeyCode::F5;
+const LOAD_KEY: KeyCode = KeyCode::F9;
+
+/// Steps that bring an older save up to date, the one at index `n` turns version `n + 1` into `n + 2`.
+/// Bump the format by appending a step, old saves keep loading through the whole chain.
+type Migration = fn(&mut Value);
+const MIGRATIONS: &[Migration] = &[];
+
+pub const SAVE_VERSION: u32 = MIGRATIONS.len() as u32 + 1;
+
+/// The parts of the player that go into a save.
+type SavedPlayer = (&'static Transform, &'static Health, &'static Stats, &'static Experience, &'static SkillLoadout, &'static Inventory);
+/// The same parts, to be overwritten from a save.
+type RestoredPlayer = (&'static mut Transform, &'static mut Health, &'static mut Stats, &'static mut Experience, &'static mut SkillLoadout, &'static mut Inventory);
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems((
+                save_game,
+                load_game.before(change_level).before(reset_spawner),
+                apply_pending_load.before(load_game),
+            ).in_set(OnUpdate(GameState::Playing)));
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct PlayerSave {
+    pub position: (f32, f32),
+    pub health: f32,
+    pub stats: Stats,
+    pub experience: Experience,
+    pub skills: SkillLoadout,
+    pub inventory: Inventory,
+}
+
+/// Everything needed to pick a run back up, enemies of the saved wave are spawned anew.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct SaveGame {
+    pub version: u32,
+    pub level: usize,
+    pub wave: usize,
+    pub score: u32,
+    pub player: PlayerSave,
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Storage(String),
+    Format(serde_json::Error),
+    UnknownVersion(u64),
+    UnknownLevel(usize),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Storage(message) => write!(f, "storage error: {}", message),
+            SaveError::Format(error) => write!(f, "malformed save: {}", error),
+            SaveError::UnknownVersion(version) => write!(f, "unknown save version {}", version),
+            SaveError::UnknownLevel(level) => write!(f, "unknown level {}", level),
+        }
+    }
+}
+
+impl From<serde_json::Error> for SaveError {
+    fn from(error: serde_json::Error) -> Self {
+        SaveError::Format(error)
+    }
+}
+
+/// Runs every migration from the save's version on and stamps it with the latest one.
+pub fn migrate(mut save: Value, migrations: &[Migration]) -> Result<Value, SaveError> {
+    let latest = migrations.len() as u64 + 1;
+    let version = save.get("version").and_then(Value::as_u64).unwrap_or(0);
+    if version == 0 || version > latest {
+        return Err(SaveError::UnknownVersion(version));
+    }
+    for migration in &migrations[version as usize - 1..] {
+        migration(&mut save);
+    }
+    save["version"] = latest.into();
+    Ok(save)
+}
+
+/// Saves pointing at a level the game no longer has are rejected whole, rather than restoring the player elsewhere.
+pub fn parse_save(text: &str) -> Result<SaveGame, SaveError> {
+    let save: SaveGame = serde_json::from_value(migrate(serde_json::from_str(text)?, MIGRATIONS)?)?;
+    if save.level >= LEVELS.len() {
+        return Err(SaveError::UnknownLevel(save.level));
+    }
+    Ok(save)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod storage {
+    use super::SaveError;
+
+    const SAVE_FILE: &str = "savegame.json";
+
+    pub fn write(text: &str) -> Result<(), SaveError> {
+        std::fs::write(SAVE_FILE, text).map_err(|error| SaveError::Storage(error.to_string()))
+    }
+
+    pub fn read() -> Result<String, SaveError> {
+        std::fs::read_to_string(SAVE_FILE).map_err(|error| SaveError::Storage(error.to_string()))
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod storage {
+    use super::SaveError;
+
+    const STORAGE_KEY: &str = "rotary-phone-save";
+
+    fn local_storage() -> Result<web_sys::Storage, SaveError> {
+        web_sys::window()
+            .and_then(|window| window.local_storage().ok().flatten())
+            .ok_or_else(|| SaveError::Storage("localStorage is not available".to_string()))
+    }
+
+    pub fn write(text: &str) -> Result<(), SaveError> {
+        local_storage()?
+            .set_item(STORAGE_KEY, text)
+            .map_err(|_| SaveError::Storage("localStorage refused the save".to_string()))
+    }
+
+    pub fn read() -> Result<String, SaveError> {
+        local_storage()?
+            .get_item(STORAGE_KEY)
+            .ok()
+            .flatten()
+            .ok_or_else(|| SaveError::Storage("no save in localStorage".to_string()))
+    }
+}
+
+/// Save waiting for the level it belongs to to be rebuilt.
+#[derive(Resource)]
+struct PendingLoad(SaveGame);
+
+fn save_game(
+    keyboard_input: Res<Input<KeyCode>>,
+    current_level: Res<CurrentLevel>,
+    spawner: Res<Spawner>,
+    score: Res<Score>,
+    player_q: Query<SavedPlayer, With<PlayerControlled>>,
+) {
+    if !keyboard_input.just_pressed(SAVE_KEY) {
+        return;
+    }
+    let Ok((transform, health, stats, experience, skills, inventory)) = player_q.get_single() else {
+        return;
+    };
+    let save = SaveGame {
+        version: SAVE_VERSION,
+        level: current_level.0,
+        wave: spawner.wave,
+        score: score.0,
+        player: PlayerSave {
+            position: (transform.translation.x, transform.translation.y),
+            health: health.act,
+            stats: stats.clone(),
+            experience: experience.clone(),
+            skills: skills.clone(),
+            inventory: inventory.clone(),
+        },
+    };
+    let result = serde_json::to_string_pretty(&save)
+        .map_err(SaveError::from)
+        .and_then(|text| storage::write(&text));
+    match result {
+        Ok(()) => info!("game saved"),
+        Err(error) => warn!("could not save the game: {}", error),
+    }
+}
+
+/// The level is rebuilt first, the player is restored over its spawn on the next frame.
+fn load_game(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut change_level_events: EventWriter<ChangeLevel>,
+) {
+    if !keyboard_input.just_pressed(LOAD_KEY) {
+        return;
+    }
+    match storage::read().and_then(|text| parse_save(&text)) {
+        Ok(save) => {
+            change_level_events.send(ChangeLevel(save.level));
+            commands.insert_resource(PendingLoad(save));
+        },
+        Err(error) => warn!("could not load the game: {}", error),
+    }
+}
+
+fn apply_pending_load(
+    mut commands: Commands,
+    pending: Option<Res<PendingLoad>>,
+    mut spawner: ResMut<Spawner>,
+    mut score: ResMut<Score>,
+    mut player_q: Query<RestoredPlayer, With<PlayerControlled>>,
+) {
+    let Some(pending) = pending else {
+        return;
+    };
+    let Ok((mut transform, mut health, mut stats, mut experience, mut skills, mut inventory)) = player_q.get_single_mut() else {
+        return;
+    };
+    let save = &pending.0;
+    transform.translation.x = save.player.position.0;
+    transform.translation.y = save.player.position.1;
+    *stats = save.player.stats.clone();
+    health.max = stats.max_health;
+    health.act = save.player.health.min(health.max);
+    *experience = save.player.experience.clone();
+    *skills = save.player.skills.clone();
+    *inventory = save.player.inventory.clone();
+    *spawner = Spawner::resume_at(save.wave);
+    score.0 = save.score;
+    commands.remove_resource::<PendingLoad>();
+    info!("game loaded");
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::components::{Stats, Experience, SkillLoadout, Inventory, Item};
+
+    use super::{SaveGame, PlayerSave, SaveError, SAVE_VERSION, migrate, parse_save};
+
+    fn save() -> SaveGame {
+        SaveGame {
+            version: SAVE_VERSION,
+            level: 1,
+            wave: 3,
+            score: 120,
+            player: PlayerSave {
+                position: (10.0, -20.0),
+                health: 4.0,
+                stats: Stats::new(6.0),
+                experience: Experience::default(),
+                skills: SkillLoadout::new(&["punch", "slash"]).with_secondary("fireball"),
+                inventory: Inventory { coins: 7, items: vec![Item::HealthPotion { heal: 1.0 }] },
+            },
+        }
+    }
+
+    #[test]
+    fn save_round_trips() {
+        let text = serde_json::to_string(&save()).unwrap();
+        assert_eq!(parse_save(&text).unwrap(), save());
+    }
+
+    #[test]
+    fn newer_and_unversioned_saves_are_rejected() {
+        let mut newer = serde_json::to_value(save()).unwrap();
+        newer["version"] = (SAVE_VERSION + 1).into();
+        assert!(matches!(parse_save(&newer.to_string()), Err(SaveError::UnknownVersion(_))));
+        assert!(matches!(parse_save("{}"), Err(SaveError::UnknownVersion(0))));
+    }
+
+    #[test]
+    fn saves_of_missing_levels_are_rejected() {
+        let mut missing = save();
+        missing.level = 99;
+        let text = serde_json::to_string(&missing).unwrap();
+        assert!(matches!(parse_save(&text), Err(SaveError::UnknownLevel(99))));
+    }
+
+    #[test]
+    fn migrations_run_from_the_save_version_on() {
+        fn add_score(save: &mut Value) {
+            save["score"] = 0.into();
+        }
+        fn double_score(save: &mut Value) {
+            save["score"] = (save["score"].as_u64().unwrap() * 2).into();
+        }
+        let migrations = [add_score, double_score];
+
+        let migrated = migrate(json!({ "version": 1 }), &migrations).unwrap();
+        assert_eq!(migrated, json!({ "version": 3, "score": 0 }));
+        let migrated = migrate(json!({ "version": 2, "score": 5 }), &migrations).unwrap();
+        assert_eq!(migrated, json!({ "version": 3, "score": 10 }));
+    }
+}
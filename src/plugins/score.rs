@@ -0,0 +1,54 @@
+use bevy::prelude::*;
+
+use crate::{GameState, components::PlayerControlled};
+
+use super::{events::{Died, WaveCleared}, damage::resolve_damage};
+
+const KILL_SCORE: u32 = 10;
+/// Clearing a wave is worth this times the wave number.
+const WAVE_SCORE: u32 = 50;
+
+pub struct ScorePlugin;
+
+impl Plugin for ScorePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<Score>()
+            .add_systems((
+                score_kills.after(resolve_damage),
+                score_waves,
+            ).in_set(OnUpdate(GameState::Playing)))
+            .add_system(reset_score.in_schedule(OnEnter(GameState::MainMenu)))
+            .add_system(reset_score.in_schedule(OnExit(GameState::GameOver)));
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct Score(pub u32);
+
+fn reset_score(
+    mut score: ResMut<Score>,
+) {
+    score.0 = 0;
+}
+
+fn score_kills(
+    mut score: ResMut<Score>,
+    mut died_events: EventReader<Died>,
+    player_q: Query<(), With<PlayerControlled>>,
+) {
+    for ev in died_events.iter() {
+        if player_q.get(ev.killer).is_ok() {
+            score.0 += KILL_SCORE;
+        }
+    }
+}
+
+fn score_waves(
+    mut score: ResMut<Score>,
+    mut wave_cleared: EventReader<WaveCleared>,
+) {
+    for ev in wave_cleared.iter() {
+        score.0 += WAVE_SCORE * ev.wave as u32;
+    }
+}
@@ -1,12 +1,19 @@
-use std::f32::consts::{FRAC_PI_2, FRAC_PI_4};
+use std::f32::consts::FRAC_PI_2;
 
-use bevy::{prelude::*, math::vec2};
+use bevy::{
+    prelude::*,
+    math::vec2,
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    reflect::TypeUuid,
+    utils::{BoxedFuture, HashMap, HashSet},
+};
 use bevy_rapier2d::prelude::{
     RigidBody,
     Collider,
     Velocity,
     Sensor, ActiveCollisionTypes, ActiveEvents
 };
+use serde::Deserialize;
 
 use crate::{
     GameState,
@@ -15,140 +22,351 @@ use crate::{
     components::{
         Projectile,
         RotateAroundPoint,
-        Slash, Attack, TTL, AttackCD, EquippedSkill
+        Slash, Attack, TTL, AttackOwner, Faction, MeleeSide, Cooldowns, Animation, AnimationState, DamageKind, StatusEffect,
+        Character, FromSkill, HitTargets, Homing, Piercing, Bouncing, Splitting, Explosive, ParticleTrail, Dying, Stats,
     },
     ATTACK_Z_INDEX,
-    FIREBALL_SPEED,
-    SLASH_SPEED, SPRITE_SIZE, PUNCH_SPEED
 };
 
-use super::events::{SkillEvent};
+use super::{events::{SkillEvent, ProjectileImpact}, collision::{FactionRelations, handle_events}};
+
+/// Homing projectiles only notice targets this close.
+const HOMING_RANGE: f32 = SPRITE_DRAW_SIZE * 6.0;
+
+/// The character using a skill: its faction, the side it swings from, its cooldowns, its clips and the stats shortening its cooldowns.
+type Casters = (Option<&'static Faction>, Option<&'static mut MeleeSide>, Option<&'static mut Cooldowns>, Option<&'static mut Animation>, Option<&'static Stats>);
+/// Characters homing projectiles may still turn toward.
+type HomingTargets = (With<Character>, Without<Dying>);
 
 pub struct SkillsPlugin;
 
 impl Plugin for SkillsPlugin {
     fn build(&self, app: &mut App) {
       app
+        .add_asset::<SkillBook>()
+        .init_asset_loader::<SkillBookLoader>()
         .add_systems((
             on_skill_used,
+            steer_homing_projectiles,
+            split_projectiles.after(handle_events),
         ).in_set(OnUpdate(GameState::Playing)));
     }
 }
 
+/// Collider of a spawned attack, all sizes are relative to `SPRITE_DRAW_SIZE`.
+#[derive(Deserialize, Clone, Debug)]
+pub enum SkillShape {
+    Cuboid { half_width: f32, half_height: f32 },
+    Ball { radius: f32 },
+    Polyline(Vec<(f32, f32)>),
+}
 
+impl SkillShape {
+    pub fn collider(&self) -> Collider {
+        match self {
+            SkillShape::Cuboid { half_width, half_height } => Collider::cuboid(SPRITE_DRAW_SIZE * half_width, SPRITE_DRAW_SIZE * half_height),
+            SkillShape::Ball { radius } => Collider::ball(SPRITE_DRAW_SIZE * radius),
+            SkillShape::Polyline(points) => Collider::polyline(
+                points.iter().map(|(x, y)| vec2(SPRITE_DRAW_SIZE * x, SPRITE_DRAW_SIZE * y)).collect(),
+                None
+            ),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub enum SkillMotion {
+    /// Flies away from the caster in a straight line at `speed`.
+    Projectile,
+    /// Sweeps around the cast position at `speed` radians per second, starting `start_angle` away from the aim.
+    Orbit { start_angle: f32 },
+    /// Short range projectile alternating between the caster's sides, `side_offset` is relative to `SPRITE_DRAW_SIZE`.
+    Melee { side_offset: f32 },
+}
+
+/// Behaviours layered on top of the attack's motion, they combine freely.
+#[derive(Deserialize, Clone, Debug)]
+pub enum ProjectileModifier {
+    Homing { turn_rate: f32 },
+    Piercing { count: u32 },
+    Bouncing { count: u32 },
+    Splitting { count: u32, spread: f32 },
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct SkillDefinition {
+    pub damage: f32,
+    #[serde(default)]
+    pub kind: DamageKind,
+    pub cooldown: f32,
+    pub lifetime: f32,
+    pub speed: f32,
+    /// Name of the sprite in the sprite sheet.
+    pub sprite: String,
+    /// Distance the target is pushed away, in pixels.
+    #[serde(default)]
+    pub knockback: f32,
+    /// Status effects applied to every target hit.
+    #[serde(default)]
+    pub effects: Vec<StatusEffect>,
+    #[serde(default)]
+    pub modifiers: Vec<ProjectileModifier>,
+    #[serde(default)]
+    pub explosion: Option<Explosive>,
+    /// Particle emitter the attack leaves behind while it flies.
+    #[serde(default)]
+    pub trail: Option<String>,
+    /// Sprite rotation relative to the aim direction, in radians.
+    pub rotation: f32,
+    /// Distance in front of the caster the attack is spawned at, relative to `SPRITE_DRAW_SIZE`.
+    pub spawn_distance: f32,
+    pub collider: SkillShape,
+    pub motion: SkillMotion,
+}
 
-fn on_skill_used(
+#[derive(Deserialize, TypeUuid, Debug)]
+#[uuid = "5f0c4e0e-5a3b-4a7e-9d7a-6c1f6b0d2e41"]
+pub struct SkillBook {
+    pub skills: HashMap<String, SkillDefinition>,
+}
+
+#[derive(Default)]
+pub struct SkillBookLoader;
+
+impl AssetLoader for SkillBookLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let skill_book = ron::de::from_bytes::<SkillBook>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(skill_book));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["skills.ron"]
+    }
+}
+
+pub fn on_skill_used(
     game_resources: Res<GameResources>,
+    skill_books: Res<Assets<SkillBook>>,
     mut commands: Commands,
-    mut slash_events: EventReader<SkillEvent>,
+    mut skill_events: EventReader<SkillEvent>,
+    mut caster_q: Query<Casters>,
 ) {
+    let Some(skill_book) = skill_books.get(&game_resources.skills_handle) else {
+        return;
+    };
 
-    for ev in slash_events.iter() {
-        match ev.kind {
-            EquippedSkill::Slash => {
-                commands.entity(ev.parent).insert(AttackCD::new(1.0));
-
-                let offset = ev.spawn_vector_norm * SPRITE_DRAW_SIZE * 0.7;
-                let spawn_position = ev.start_position + offset;
-                let mut attack_transform = Transform::from_xyz(spawn_position.x, spawn_position.y, ATTACK_Z_INDEX)
-                    .with_rotation(Quat::from_rotation_z(ev.angle-FRAC_PI_4));
-                attack_transform.rotate_around(ev.start_position.extend(ATTACK_Z_INDEX), Quat::from_rotation_z(-FRAC_PI_4));
-
-                commands.spawn((
-                    Attack {
-                        value: 1.0,
-                    },
-                    RotateAroundPoint::new(ev.start_position.extend(ATTACK_Z_INDEX), SLASH_SPEED),
-                    Slash,
-                    TTL::new(0.2),
-                    SpriteBundle {
-                        sprite: Sprite {
-                            custom_size: Some(vec2(SPRITE_DRAW_SIZE, SPRITE_DRAW_SIZE)),
-                            rect: Some(Rect::new(5.0 * SPRITE_SIZE, 0.0, 6.0 * SPRITE_SIZE, SPRITE_SIZE)),
-                            ..default()
-                        },
-                        texture: game_resources.image_handle.clone(),
-                        transform: attack_transform,
-                        ..default()
-                    },
-                    RigidBody::Dynamic,
-                    Sensor,
-                    Collider::polyline(vec![
-                        vec2(-SPRITE_DRAW_SIZE * 0.35, SPRITE_DRAW_SIZE * 0.35),
-                        vec2(SPRITE_DRAW_SIZE * 0.2, SPRITE_DRAW_SIZE * 0.2),
-                        vec2(SPRITE_DRAW_SIZE * 0.35, -SPRITE_DRAW_SIZE * 0.35)
-                    ], None),
-                    ActiveCollisionTypes::default() | ActiveCollisionTypes::KINEMATIC_STATIC,
-                    ActiveEvents::COLLISION_EVENTS,
-                ));
-            },
-            EquippedSkill::FireBall => {
-                commands.entity(ev.parent).insert(AttackCD::new(2.0));
-
-                let spawn_position = ev.start_position + ev.spawn_vector_norm * SPRITE_DRAW_SIZE;
-                commands.spawn((
-                    Attack {
-                        value: 1.5,
-                    },
-                    TTL::new(0.7),
-                    SpriteBundle {
-                        sprite: Sprite {
-                            custom_size: Some(vec2(SPRITE_DRAW_SIZE, SPRITE_DRAW_SIZE)),
-                            rect: Some(Rect::new(3.0 * SPRITE_SIZE, 0.0, 4.0 * SPRITE_SIZE, SPRITE_SIZE)),
-                            ..default()
-                        },
-                        texture: game_resources.image_handle.clone(),
-                        transform: Transform::from_xyz(spawn_position.x, spawn_position.y, ATTACK_Z_INDEX).with_rotation(Quat::from_rotation_z(ev.angle+FRAC_PI_2)),
-                        ..default()
-                    },
-                    RigidBody::Dynamic,
-                    Sensor,
-                    Collider::cuboid(SPRITE_DRAW_SIZE / 2.0 - 10.0, SPRITE_DRAW_SIZE / 2.0 - 10.0),
-                    ActiveEvents::COLLISION_EVENTS,
-                    Velocity {
-                        linvel: Vec2::new(ev.spawn_vector_norm.x * FIREBALL_SPEED, ev.spawn_vector_norm.y * FIREBALL_SPEED),
-                        ..default()
-                    },
-                    Projectile
-                ));
-            },
-            EquippedSkill::Punch(punch_offset) => {
-                commands.entity(ev.parent).insert(AttackCD::new(0.1));
-                commands.entity(ev.parent).remove::<EquippedSkill>();
-                commands.entity(ev.parent).insert(EquippedSkill::Punch(-punch_offset));
-
-                let spawn_position = ev.start_position +
-                    ev.spawn_vector_norm * SPRITE_DRAW_SIZE * 0.8 +
-                    vec2((ev.angle - FRAC_PI_2).cos(), (ev.angle - FRAC_PI_2).sin()) * punch_offset;
-                commands.spawn((
-                    Attack {
-                        value: 0.5,
-                    },
-                    TTL::new(0.05),
-                    SpriteBundle {
-                        sprite: Sprite {
-                            custom_size: Some(vec2(SPRITE_DRAW_SIZE, SPRITE_DRAW_SIZE)),
-                            rect: Some(Rect::new(7.0 * SPRITE_SIZE, 0.0, 8.0 * SPRITE_SIZE, SPRITE_SIZE)),
-                            ..default()
-                        },
-                        texture: game_resources.image_handle.clone(),
-                        transform: Transform::from_xyz(spawn_position.x, spawn_position.y, ATTACK_Z_INDEX)
-                            .with_rotation(Quat::from_rotation_z(ev.angle)),
-                        ..default()
-                    },
-                    RigidBody::Dynamic,
-                    Sensor,
-                    Collider::cuboid(SPRITE_DRAW_SIZE * 0.2, SPRITE_DRAW_SIZE * 0.2),
-                    ActiveEvents::COLLISION_EVENTS,
-                    Velocity {
-                        linvel: Vec2::new(ev.spawn_vector_norm.x * PUNCH_SPEED, ev.spawn_vector_norm.y * PUNCH_SPEED),
-                        ..default()
-                    },
-                    Projectile
-                ));
+    for ev in skill_events.iter() {
+        let Some(skill) = skill_book.skills.get(&ev.skill) else {
+            warn!("unknown skill {}", ev.skill);
+            continue;
+        };
+        let Ok((faction, melee_side, cooldowns, animation, stats)) = caster_q.get_mut(ev.parent) else {
+            continue;
+        };
+        if let Some(mut cooldowns) = cooldowns {
+            if !cooldowns.is_ready(&ev.skill) {
+                continue;
             }
+            let reduction = stats.map_or(0.0, |stats| stats.cooldown_reduction);
+            cooldowns.start(&ev.skill, skill.cooldown * (1.0 - reduction));
+        }
+        if let Some(mut animation) = animation {
+            animation.play(AnimationState::Attack);
+        }
+
+        let owner = AttackOwner {
+            entity: ev.parent,
+            faction: faction.copied().unwrap_or(Faction::Neutral),
+        };
+        let spawn_position = ev.start_position + ev.spawn_vector_norm * SPRITE_DRAW_SIZE * skill.spawn_distance;
+        let mut attack_transform = Transform::from_xyz(spawn_position.x, spawn_position.y, ATTACK_Z_INDEX)
+            .with_rotation(Quat::from_rotation_z(ev.angle + skill.rotation));
+        if let SkillMotion::Melee { side_offset } = skill.motion {
+            let side = match melee_side {
+                Some(mut melee_side) => {
+                    let side = melee_side.0;
+                    melee_side.0 = -side;
+                    side
+                },
+                None => {
+                    commands.entity(ev.parent).insert(MeleeSide(-1.0));
+                    1.0
+                }
+            };
+            let side_vector = vec2((ev.angle - FRAC_PI_2).cos(), (ev.angle - FRAC_PI_2).sin());
+            attack_transform.translation += (side_vector * SPRITE_DRAW_SIZE * side_offset * side).extend(0.0);
+        }
+        if let SkillMotion::Orbit { start_angle } = skill.motion {
+            attack_transform.rotate_around(ev.start_position.extend(ATTACK_Z_INDEX), Quat::from_rotation_z(start_angle));
         }
 
+        spawn_attack(&mut commands, &game_resources, &ev.skill, skill, owner, Launch {
+            transform: attack_transform,
+            direction: ev.spawn_vector_norm,
+            origin: ev.start_position,
+        });
+    }
+}
 
+/// Where a new attack starts and where it heads.
+struct Launch {
+    transform: Transform,
+    /// Flight direction of projectiles and melee attacks.
+    direction: Vec2,
+    /// Point orbit attacks sweep around.
+    origin: Vec2,
+}
+
+/// Spawns the attack of `skill` as launched.
+fn spawn_attack(
+    commands: &mut Commands,
+    game_resources: &GameResources,
+    skill_name: &str,
+    skill: &SkillDefinition,
+    owner: AttackOwner,
+    launch: Launch,
+) -> Entity {
+    let Launch { transform, direction, origin } = launch;
+    let mut attack = commands.spawn((
+        Attack {
+            value: skill.damage,
+            kind: skill.kind,
+            knockback: skill.knockback,
+            effects: skill.effects.clone(),
+        },
+        owner,
+        FromSkill(skill_name.to_string()),
+        HitTargets::default(),
+        TTL::new(skill.lifetime),
+        SpriteSheetBundle {
+            sprite: TextureAtlasSprite {
+                custom_size: Some(vec2(SPRITE_DRAW_SIZE, SPRITE_DRAW_SIZE)),
+                ..default()
+            },
+            texture_atlas: game_resources.atlas_handle.clone(),
+            transform,
+            ..default()
+        },
+        Animation::new(skill.sprite.clone()),
+        RigidBody::Dynamic,
+        Sensor,
+        skill.collider.collider(),
+        ActiveEvents::COLLISION_EVENTS,
+    ));
+    match skill.motion {
+        SkillMotion::Projectile | SkillMotion::Melee { .. } => {
+            attack.insert((
+                Velocity {
+                    linvel: direction * skill.speed,
+                    ..default()
+                },
+                Projectile
+            ));
+        },
+        SkillMotion::Orbit { .. } => {
+            attack.insert((
+                RotateAroundPoint::new(origin.extend(ATTACK_Z_INDEX), skill.speed),
+                Slash,
+                ActiveCollisionTypes::default() | ActiveCollisionTypes::KINEMATIC_STATIC,
+            ));
+        }
+    }
+    if let Some(trail) = &skill.trail {
+        attack.insert(ParticleTrail::new(trail.clone()));
+    }
+    if let Some(explosive) = &skill.explosion {
+        attack.insert(explosive.clone());
+    }
+    for modifier in skill.modifiers.iter() {
+        match *modifier {
+            ProjectileModifier::Homing { turn_rate } => attack.insert(Homing { turn_rate }),
+            ProjectileModifier::Piercing { count } => attack.insert(Piercing(count)),
+            ProjectileModifier::Bouncing { count } => attack.insert(Bouncing(count)),
+            ProjectileModifier::Splitting { count, spread } => attack.insert(Splitting { count, spread }),
+        };
+    }
+    attack.id()
+}
+
+/// Turns homing projectiles toward the nearest hostile character they did not hit yet.
+fn steer_homing_projectiles(
+    time: Res<Time>,
+    relations: Res<FactionRelations>,
+    mut projectile_q: Query<(&Homing, &AttackOwner, Option<&HitTargets>, &mut Transform, &mut Velocity)>,
+    target_q: Query<(Entity, &Faction, &GlobalTransform), HomingTargets>,
+) {
+    let dt = time.delta_seconds();
+    for (homing, owner, hit_targets, mut transform, mut velocity) in projectile_q.iter_mut() {
+        let position = transform.translation.truncate();
+        let nearest = target_q.iter()
+            .filter(|(entity, faction, _)| {
+                relations.is_hostile(owner.faction, **faction)
+                    && !hit_targets.is_some_and(|hit_targets| hit_targets.0.contains(entity))
+            })
+            .map(|(_, _, target_transform)| target_transform.translation().truncate() - position)
+            .filter(|offset| offset.length() < HOMING_RANGE && *offset != Vec2::ZERO)
+            .min_by(|a, b| a.length_squared().total_cmp(&b.length_squared()));
+        let Some(offset) = nearest else {
+            continue;
+        };
+        if velocity.linvel == Vec2::ZERO {
+            continue;
+        }
+        let max_turn = homing.turn_rate * dt;
+        let turn = velocity.linvel.angle_between(offset).clamp(-max_turn, max_turn);
+        velocity.linvel = Vec2::from_angle(turn).rotate(velocity.linvel);
+        transform.rotate_z(turn);
+    }
+}
+
+/// Fragments fan out around the flight direction of the spent projectile and spare the target it hit.
+fn split_projectiles(
+    game_resources: Res<GameResources>,
+    skill_books: Res<Assets<SkillBook>>,
+    mut commands: Commands,
+    mut impact_events: EventReader<ProjectileImpact>,
+    projectile_q: Query<(&FromSkill, &AttackOwner, &Splitting, &Transform, &Velocity)>,
+) {
+    let Some(skill_book) = skill_books.get(&game_resources.skills_handle) else {
+        return;
+    };
+
+    for ev in impact_events.iter() {
+        let Ok((from_skill, owner, splitting, transform, velocity)) = projectile_q.get(ev.attack) else {
+            continue;
+        };
+        let Some(skill) = skill_book.skills.get(&from_skill.0) else {
+            continue;
+        };
+        let direction = velocity.linvel.normalize_or_zero();
+        if direction == Vec2::ZERO {
+            continue;
+        }
+        for i in 0..splitting.count {
+            let offset = if splitting.count > 1 {
+                -splitting.spread + 2.0 * splitting.spread * i as f32 / (splitting.count - 1) as f32
+            } else {
+                0.0
+            };
+            let fragment_direction = Vec2::from_angle(offset).rotate(direction);
+            let angle = fragment_direction.y.atan2(fragment_direction.x);
+            let fragment_transform = Transform::from_translation(transform.translation)
+                .with_rotation(Quat::from_rotation_z(angle + skill.rotation));
+            let fragment = spawn_attack(&mut commands, &game_resources, &from_skill.0, skill, *owner, Launch {
+                transform: fragment_transform,
+                direction: fragment_direction,
+                origin: transform.translation.truncate(),
+            });
+            commands.entity(fragment)
+                .remove::<Splitting>()
+                .insert(HitTargets(HashSet::from_iter([ev.target])));
+        }
     }
 }
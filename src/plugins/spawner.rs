@@ -0,0 +1,337 @@
+use std::{collections::VecDeque, time::Duration};
+
+use bevy::{
+    prelude::*,
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+    ecs::{schedule::common_conditions::on_event, system::SystemParam},
+};
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::{
+    GameState,
+    GameResources,
+    spawn_enemy,
+    components::{Character, EnemySpawnPoint, Faction, Health, Dying},
+};
+
+use super::{ai::EnemyArchetype, events::{ChangeLevel, WaveStarted, WaveCleared}, rng::GameRng};
+
+/// Seconds between entering a level and its first wave.
+const FIRST_WAVE_DELAY: f32 = 1.0;
+
+pub struct SpawnerPlugin;
+
+impl Plugin for SpawnerPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_asset::<WaveBook>()
+            .init_asset_loader::<WaveBookLoader>()
+            .init_resource::<Spawner>()
+            .add_system(run_spawner.in_set(OnUpdate(GameState::Playing)))
+            .add_system(reset_spawner.run_if(on_event::<ChangeLevel>()).in_set(OnUpdate(GameState::Playing)))
+            .add_system(reset_spawner.in_schedule(OnEnter(GameState::MainMenu)))
+            .add_system(reset_spawner.in_schedule(OnExit(GameState::GameOver)));
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub enum NextWave {
+    /// Next wave comes once every enemy of this one is dead.
+    Cleared,
+    /// Next wave comes after the given seconds, whether this one is cleared or not.
+    Timed(f32),
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct WaveGroup {
+    pub archetype: EnemyArchetype,
+    pub count: usize,
+    /// Seconds between two spawns of the group.
+    pub delay: f32,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct WaveDefinition {
+    pub groups: Vec<WaveGroup>,
+    pub next: NextWave,
+}
+
+/// Waves are played in order and start over once all of them were played,
+/// each time around with more and tougher enemies.
+#[derive(Deserialize, TypeUuid, Debug)]
+#[uuid = "4e8b2a17-6d3c-4f95-a1b0-8c7d2e6f3a59"]
+pub struct WaveBook {
+    pub waves: Vec<WaveDefinition>,
+    /// Seconds between clearing a wave and the next one.
+    pub break_time: f32,
+    /// Share of enemies added to every group on each time around.
+    pub count_growth: f32,
+    /// Share of health added to every enemy on each time around.
+    pub health_growth: f32,
+}
+
+impl WaveBook {
+    /// Definition of the wave with the given number, counted from 1, wave 0 is treated as the first one.
+    pub fn wave(&self, number: usize) -> Option<&WaveDefinition> {
+        if self.waves.is_empty() {
+            return None;
+        }
+        self.waves.get(number.saturating_sub(1) % self.waves.len())
+    }
+
+    /// How many times around the wave list the given wave is.
+    fn round(&self, number: usize) -> f32 {
+        (number.saturating_sub(1) / self.waves.len().max(1)) as f32
+    }
+
+    pub fn count(&self, number: usize, group: &WaveGroup) -> usize {
+        (group.count as f32 * (1.0 + self.round(number) * self.count_growth)).ceil() as usize
+    }
+
+    pub fn health_multiplier(&self, number: usize) -> f32 {
+        1.0 + self.round(number) * self.health_growth
+    }
+}
+
+#[derive(Default)]
+pub struct WaveBookLoader;
+
+impl AssetLoader for WaveBookLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let wave_book = ron::de::from_bytes::<WaveBook>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(wave_book));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["waves.ron"]
+    }
+}
+
+#[derive(Resource)]
+pub struct Spawner {
+    /// Number of the latest wave, 0 before the first one.
+    pub wave: usize,
+    /// Enemies of the current wave still to come, with the seconds to wait before each.
+    queue: VecDeque<(EnemyArchetype, f32)>,
+    spawn_timer: Timer,
+    next_wave: Option<Timer>,
+    cleared: bool,
+}
+
+impl Default for Spawner {
+    fn default() -> Self {
+        Self {
+            wave: 0,
+            queue: VecDeque::new(),
+            spawn_timer: Timer::from_seconds(0.0, TimerMode::Once),
+            next_wave: Some(Timer::from_seconds(FIRST_WAVE_DELAY, TimerMode::Once)),
+            cleared: true,
+        }
+    }
+}
+
+impl Spawner {
+    /// Plays the given wave again from its start, after the usual delay.
+    pub fn resume_at(wave: usize) -> Self {
+        Self {
+            wave: wave.saturating_sub(1),
+            ..default()
+        }
+    }
+
+    /// Queues the enemies of the next wave behind the ones still to come, returns false if there is no wave to start.
+    fn start_wave(&mut self, wave_book: &WaveBook) -> bool {
+        let Some(definition) = wave_book.wave(self.wave + 1) else {
+            self.next_wave = None;
+            return false;
+        };
+        self.wave += 1;
+        self.cleared = false;
+        // leftovers of a timed wave keep their pace, a fresh queue starts right away
+        if self.queue.is_empty() {
+            self.spawn_timer = Timer::from_seconds(0.0, TimerMode::Once);
+        }
+        for group in definition.groups.iter() {
+            for _ in 0..wave_book.count(self.wave, group) {
+                self.queue.push_back((group.archetype, group.delay));
+            }
+        }
+        self.next_wave = match definition.next {
+            NextWave::Cleared => None,
+            NextWave::Timed(seconds) => Some(Timer::from_seconds(seconds, TimerMode::Once)),
+        };
+        true
+    }
+}
+
+/// Announces when waves start and when they are cleared.
+#[derive(SystemParam)]
+struct WaveEvents<'w> {
+    started: EventWriter<'w, WaveStarted>,
+    cleared: EventWriter<'w, WaveCleared>,
+}
+
+/// Where enemies come in and who is already there.
+#[derive(SystemParam)]
+struct Arena<'w, 's> {
+    rng: ResMut<'w, GameRng>,
+    spawn_point_q: Query<'w, 's, (&'static EnemySpawnPoint, &'static Transform)>,
+    enemy_q: Query<'w, 's, &'static Faction, (With<Character>, Without<Dying>)>,
+}
+
+pub fn reset_spawner(
+    mut spawner: ResMut<Spawner>,
+) {
+    *spawner = Spawner::default();
+}
+
+fn run_spawner(
+    mut commands: Commands,
+    time: Res<Time>,
+    game_resources: Res<GameResources>,
+    wave_books: Res<Assets<WaveBook>>,
+    mut spawner: ResMut<Spawner>,
+    mut wave_events: WaveEvents,
+    mut arena: Arena,
+) {
+    let Some(wave_book) = wave_books.get(&game_resources.waves_handle) else {
+        return;
+    };
+    let dt = time.delta();
+
+    let next_wave_due = spawner.next_wave.as_mut().is_some_and(|timer| timer.tick(dt).finished());
+    if next_wave_due && spawner.start_wave(wave_book) {
+        wave_events.started.send(WaveStarted { wave: spawner.wave });
+    }
+
+    let mut spawned = false;
+    let spawn_points: Vec<(&EnemySpawnPoint, &Transform)> = arena.spawn_point_q.iter().collect();
+    if !spawn_points.is_empty() && !spawner.queue.is_empty() {
+        spawner.spawn_timer.tick(dt);
+        while spawner.spawn_timer.finished() {
+            let Some((archetype, _)) = spawner.queue.pop_front() else {
+                break;
+            };
+            // points authored for the archetype come first, any point will do otherwise
+            let matching: Vec<&Transform> = spawn_points.iter()
+                .filter(|(point, _)| point.archetype == archetype)
+                .map(|(_, transform)| *transform)
+                .collect();
+            let candidates = if matching.is_empty() {
+                spawn_points.iter().map(|(_, transform)| *transform).collect()
+            } else {
+                matching
+            };
+            let position = candidates[arena.rng.gen_range(0..candidates.len())].translation.truncate();
+            let enemy = spawn_enemy(&mut commands, &game_resources, archetype, position);
+            let health = archetype.max_health() * wave_book.health_multiplier(spawner.wave);
+            commands.entity(enemy).insert(Health { act: health, max: health });
+            spawned = true;
+
+            let delay = spawner.queue.front().map_or(0.0, |(_, delay)| *delay);
+            spawner.spawn_timer.set_duration(Duration::from_secs_f32(delay));
+            spawner.spawn_timer.reset();
+        }
+    }
+
+    // enemies spawned this frame do not show up in the query yet, dying ones no longer count
+    let enemies_alive = spawned || arena.enemy_q.iter().any(|faction| *faction == Faction::Enemies);
+    if !spawner.cleared && spawner.queue.is_empty() && !enemies_alive {
+        spawner.cleared = true;
+        wave_events.cleared.send(WaveCleared { wave: spawner.wave });
+
+        if spawner.next_wave.is_none() {
+            spawner.next_wave = Some(Timer::from_seconds(wave_book.break_time, TimerMode::Once));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{WaveBook, WaveDefinition, WaveGroup, NextWave, EnemyArchetype, Spawner};
+
+    #[test]
+    fn waves_get_harder_each_time_around() {
+        let group = WaveGroup { archetype: EnemyArchetype::Brawler, count: 2, delay: 1.0 };
+        let wave_book = WaveBook {
+            waves: vec![
+                WaveDefinition { groups: vec![group.clone()], next: NextWave::Cleared },
+                WaveDefinition { groups: vec![group.clone()], next: NextWave::Timed(10.0) },
+            ],
+            break_time: 1.0,
+            count_growth: 0.5,
+            health_growth: 0.25,
+        };
+        assert_eq!(wave_book.count(1, &group), 2);
+        assert_eq!(wave_book.count(2, &group), 2);
+        assert_eq!(wave_book.count(3, &group), 3);
+        assert_eq!(wave_book.count(5, &group), 4);
+        assert_eq!(wave_book.health_multiplier(2), 1.0);
+        assert_eq!(wave_book.health_multiplier(4), 1.25);
+        assert!(matches!(wave_book.wave(3).unwrap().next, NextWave::Cleared));
+    }
+
+    #[test]
+    fn wave_zero_is_the_first_wave() {
+        let group = WaveGroup { archetype: EnemyArchetype::Brawler, count: 2, delay: 1.0 };
+        let wave_book = WaveBook {
+            waves: vec![WaveDefinition { groups: vec![group.clone()], next: NextWave::Timed(5.0) }],
+            break_time: 1.0,
+            count_growth: 0.5,
+            health_growth: 0.25,
+        };
+        assert!(matches!(wave_book.wave(0).unwrap().next, NextWave::Timed(_)));
+        assert_eq!(wave_book.count(0, &group), 2);
+        assert_eq!(wave_book.health_multiplier(0), 1.0);
+    }
+
+    #[test]
+    fn timed_wave_leaves_unspawned_enemies_queued() {
+        let wave_book = WaveBook {
+            waves: vec![
+                WaveDefinition {
+                    groups: vec![WaveGroup { archetype: EnemyArchetype::Brawler, count: 3, delay: 1.0 }],
+                    next: NextWave::Timed(2.0),
+                },
+                WaveDefinition {
+                    groups: vec![WaveGroup { archetype: EnemyArchetype::Skirmisher, count: 2, delay: 1.0 }],
+                    next: NextWave::Cleared,
+                },
+            ],
+            break_time: 1.0,
+            count_growth: 0.0,
+            health_growth: 0.0,
+        };
+        let mut spawner = Spawner::default();
+        assert!(spawner.start_wave(&wave_book));
+        spawner.queue.pop_front();
+        assert!(spawner.start_wave(&wave_book));
+        let archetypes: Vec<EnemyArchetype> = spawner.queue.iter().map(|(archetype, _)| *archetype).collect();
+        assert_eq!(archetypes, vec![
+            EnemyArchetype::Brawler,
+            EnemyArchetype::Brawler,
+            EnemyArchetype::Skirmisher,
+            EnemyArchetype::Skirmisher,
+        ]);
+    }
+
+    #[test]
+    fn empty_wave_book_schedules_nothing() {
+        let wave_book = WaveBook { waves: vec![], break_time: 1.0, count_growth: 0.5, health_growth: 0.25 };
+        let mut spawner = Spawner::default();
+        assert!(!spawner.start_wave(&wave_book));
+        assert_eq!(spawner.wave, 0);
+        assert!(spawner.cleared);
+        assert!(spawner.next_wave.is_none());
+    }
+}
@@ -0,0 +1,253 @@
+use bevy::prelude::*;
+
+use crate::{
+    GameState,
+    components::{Character, Health, StatusEffect, DamageOverTime, DamageKind, DamageModifiers, Burn, Poison, Slow, Stunned, HitFlash, TTL},
+};
+
+use super::{events::{ApplyStatusEffect, DamageEvent}, collision::handle_events, damage::resolve_damage};
+
+const MAX_POISON_STACKS: u32 = 5;
+/// Share of the slow that also goes off the damage a slowed character deals.
+const SLOW_DAMAGE_SHARE: f32 = 0.5;
+/// Extra damage taken for every poison stack.
+const POISON_DAMAGE_TAKEN_PER_STACK: f32 = 0.1;
+const FLASH_INTERVAL: f32 = 0.05;
+const FLASH_COLOR: Color = Color::rgb(1.0, 0.2, 0.2);
+const STUN_TINT: Color = Color::rgb(1.0, 1.0, 0.5);
+const BURN_TINT: Color = Color::rgb(1.0, 0.6, 0.35);
+const POISON_TINT: Color = Color::rgb(0.55, 1.0, 0.45);
+const SLOW_TINT: Color = Color::rgb(0.55, 0.7, 1.0);
+
+/// Characters tinted by their hit flash and status effects, faded by the `TTL` only dying ones carry.
+type Tinted = (
+    &'static mut TextureAtlasSprite,
+    Option<&'static HitFlash>,
+    Option<&'static Stunned>,
+    Option<&'static Burn>,
+    Option<&'static Poison>,
+    Option<&'static Slow>,
+    Option<&'static TTL>,
+);
+
+pub struct StatusPlugin;
+
+impl Plugin for StatusPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems((
+                apply_status_effects.after(handle_events),
+                tick_damage_over_time.before(resolve_damage),
+                update_damage_modifiers.before(resolve_damage),
+                update_sprite_tint,
+            ).in_set(OnUpdate(GameState::Playing)));
+    }
+}
+
+fn refresh(timer: &mut Timer, seconds: f32) {
+    *timer = Timer::from_seconds(seconds, TimerMode::Once);
+}
+
+fn apply_status_effects(
+    mut commands: Commands,
+    mut status_events: EventReader<ApplyStatusEffect>,
+    health_q: Query<&Health>,
+    mut burn_q: Query<&mut Burn>,
+    mut poison_q: Query<&mut Poison>,
+    mut slow_q: Query<&mut Slow>,
+    mut stunned_q: Query<&mut Stunned>,
+) {
+    for ev in status_events.iter() {
+        // the dead are about to be despawned
+        if health_q.get(ev.target).map_or(true, |health| health.act <= 0.0) {
+            continue;
+        }
+        let Some(mut target) = commands.get_entity(ev.target) else {
+            continue;
+        };
+        match ev.effect {
+            StatusEffect::Burn { damage, interval, duration } => {
+                if let Ok(mut burn) = burn_q.get_mut(ev.target) {
+                    burn.0.source = ev.source;
+                    burn.0.damage = burn.0.damage.max(damage);
+                    refresh(&mut burn.0.duration, duration);
+                } else {
+                    target.insert(Burn(DamageOverTime::new(ev.source, damage, interval, duration)));
+                }
+            },
+            StatusEffect::Poison { damage, interval, duration } => {
+                if let Ok(mut poison) = poison_q.get_mut(ev.target) {
+                    poison.stacks = (poison.stacks + 1).min(MAX_POISON_STACKS);
+                    poison.dot.source = ev.source;
+                    refresh(&mut poison.dot.duration, duration);
+                } else {
+                    target.insert(Poison {
+                        dot: DamageOverTime::new(ev.source, damage, interval, duration),
+                        stacks: 1,
+                    });
+                }
+            },
+            StatusEffect::Slow { factor, duration } => {
+                if let Ok(mut slow) = slow_q.get_mut(ev.target) {
+                    slow.factor = slow.factor.min(factor);
+                    if slow.timer.remaining_secs() < duration {
+                        refresh(&mut slow.timer, duration);
+                    }
+                } else {
+                    target.insert(Slow::new(factor, duration));
+                }
+            },
+            StatusEffect::Stun { duration } => {
+                if let Ok(mut stunned) = stunned_q.get_mut(ev.target) {
+                    if stunned.0.remaining_secs() < duration {
+                        refresh(&mut stunned.0, duration);
+                    }
+                } else {
+                    target.insert(Stunned::new(duration));
+                }
+            },
+        }
+    }
+}
+
+fn tick_damage_over_time(
+    time: Res<Time>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut burn_q: Query<(Entity, &mut Burn)>,
+    mut poison_q: Query<(Entity, &mut Poison)>,
+) {
+    let dt = time.delta();
+    for (entity, mut burn) in burn_q.iter_mut() {
+        if burn.0.tick.tick(dt).just_finished() {
+            damage_events.send(DamageEvent {
+                source: burn.0.source,
+                target: entity,
+                amount: burn.0.damage,
+                kind: DamageKind::Fire,
+            });
+        }
+    }
+    for (entity, mut poison) in poison_q.iter_mut() {
+        if poison.dot.tick.tick(dt).just_finished() {
+            damage_events.send(DamageEvent {
+                source: poison.dot.source,
+                target: entity,
+                amount: poison.dot.damage * poison.stacks as f32,
+                kind: DamageKind::Poison,
+            });
+        }
+    }
+}
+
+/// Slowed characters hit softer and every poison stack makes a character take more damage.
+fn update_damage_modifiers(
+    mut modifiers_q: Query<(&mut DamageModifiers, Option<&Slow>, Option<&Poison>)>,
+) {
+    for (mut modifiers, slow, poison) in modifiers_q.iter_mut() {
+        let dealt = slow.map_or(1.0, |slow| 1.0 - (1.0 - slow.factor) * SLOW_DAMAGE_SHARE);
+        let taken = 1.0 + poison.map_or(0.0, |poison| poison.stacks as f32 * POISON_DAMAGE_TAKEN_PER_STACK);
+        if modifiers.dealt != dealt || modifiers.taken != taken {
+            *modifiers = DamageModifiers { dealt, taken };
+        }
+    }
+}
+
+/// Hit flash blinks over the tint of the most limiting status effect, the dying fade out.
+fn update_sprite_tint(
+    mut sprite_q: Query<Tinted, With<Character>>,
+) {
+    for (mut sprite, flash, stunned, burn, poison, slow, ttl) in sprite_q.iter_mut() {
+        let flashing = flash.is_some_and(|flash| (flash.0.elapsed_secs() / FLASH_INTERVAL) as u32 & 1 == 0);
+        let color = if flashing {
+            FLASH_COLOR
+        } else if stunned.is_some() {
+            STUN_TINT
+        } else if burn.is_some() {
+            BURN_TINT
+        } else if poison.is_some() {
+            POISON_TINT
+        } else if slow.is_some() {
+            SLOW_TINT
+        } else {
+            Color::WHITE
+        };
+        let color = color.with_a(ttl.map_or(1.0, |ttl| ttl.0.percent_left()));
+        if sprite.color != color {
+            sprite.color = color;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use crate::{
+        GameState,
+        components::{Health, StatusEffect, DamageModifiers, Burn, Poison, Stunned},
+        plugins::{events::{ApplyStatusEffect, DamageEvent, ProjectileImpact, SpawnParticles}, collision::CollisionPlugin},
+    };
+
+    use super::{StatusPlugin, MAX_POISON_STACKS, POISON_DAMAGE_TAKEN_PER_STACK, SLOW_DAMAGE_SHARE};
+
+    fn test_app() -> App {
+        let mut app = App::new();
+        app
+            .add_plugins(MinimalPlugins)
+            .add_state::<GameState>()
+            .add_event::<bevy_rapier2d::prelude::CollisionEvent>()
+            .add_event::<DamageEvent>()
+            .add_event::<ApplyStatusEffect>()
+            .add_event::<ProjectileImpact>()
+            .add_event::<SpawnParticles>()
+            .add_plugin(CollisionPlugin)
+            .add_plugin(StatusPlugin);
+        app.world.insert_resource(State(GameState::Playing));
+        app
+    }
+
+    fn apply(app: &mut App, target: Entity, effect: StatusEffect) {
+        app.world.send_event(ApplyStatusEffect { source: target, target, effect });
+        app.update();
+    }
+
+    #[test]
+    fn poison_stacks_up_to_the_limit() {
+        let mut app = test_app();
+        let target = app.world.spawn(Health { act: 3.0, max: 3.0 }).id();
+        for _ in 0..MAX_POISON_STACKS + 2 {
+            apply(&mut app, target, StatusEffect::Poison { damage: 0.1, interval: 1.0, duration: 3.0 });
+        }
+        assert_eq!(app.world.get::<Poison>(target).unwrap().stacks, MAX_POISON_STACKS);
+    }
+
+    #[test]
+    fn burn_refreshes_and_stun_keeps_the_longest() {
+        let mut app = test_app();
+        let target = app.world.spawn(Health { act: 3.0, max: 3.0 }).id();
+
+        apply(&mut app, target, StatusEffect::Burn { damage: 0.5, interval: 1.0, duration: 2.0 });
+        apply(&mut app, target, StatusEffect::Burn { damage: 0.25, interval: 1.0, duration: 4.0 });
+        let burn = app.world.get::<Burn>(target).unwrap();
+        assert_eq!(burn.0.damage, 0.5);
+        assert_eq!(burn.0.duration.duration().as_secs_f32(), 4.0);
+
+        apply(&mut app, target, StatusEffect::Stun { duration: 2.0 });
+        apply(&mut app, target, StatusEffect::Stun { duration: 0.5 });
+        assert_eq!(app.world.get::<Stunned>(target).unwrap().0.duration().as_secs_f32(), 2.0);
+    }
+
+    #[test]
+    fn slow_and_poison_drive_damage_modifiers() {
+        let mut app = test_app();
+        let target = app.world.spawn((Health { act: 3.0, max: 3.0 }, DamageModifiers::default())).id();
+
+        apply(&mut app, target, StatusEffect::Slow { factor: 0.5, duration: 2.0 });
+        apply(&mut app, target, StatusEffect::Poison { damage: 0.1, interval: 1.0, duration: 3.0 });
+        apply(&mut app, target, StatusEffect::Poison { damage: 0.1, interval: 1.0, duration: 3.0 });
+        app.update();
+        let modifiers = app.world.get::<DamageModifiers>(target).unwrap();
+        assert_eq!(modifiers.dealt, 1.0 - 0.5 * SLOW_DAMAGE_SHARE);
+        assert_eq!(modifiers.taken, 1.0 + 2.0 * POISON_DAMAGE_TAKEN_PER_STACK);
+    }
+}
@@ -1,6 +1,8 @@
+use std::time::Duration;
+
 use bevy::prelude::*;
 
-use crate::{GameState, components::{TTL, AttackCD}};
+use crate::{GameState, components::{TTL, Cooldowns, Knockback, Stunned, Invulnerable, HitFlash, Burn, Poison, Slow}};
 
 pub struct TimersPlugin;
 
@@ -8,7 +10,14 @@ impl Plugin for TimersPlugin {
     fn build(&self, app: &mut App) {
         app
             .add_systems((
-                update_cd::<AttackCD>,
+                update_cd::<Cooldowns>,
+                update_cd::<Knockback>,
+                update_cd::<Stunned>,
+                update_cd::<Invulnerable>,
+                update_cd::<HitFlash>,
+                update_cd::<Burn>,
+                update_cd::<Poison>,
+                update_cd::<Slow>,
                 update_ttl,
             ).in_set(OnUpdate(GameState::Playing)));
     }
@@ -19,23 +28,36 @@ pub trait WithTimer {
     fn timer_mut(&mut self) -> &mut Timer;
 }
 
+/// What `update_cd` ticks: a `WithTimer` component is spent once its timer finishes,
+/// components holding several cooldowns tick all of them and stay.
+pub trait Cooldown {
+    /// Advances the timers by `dt`, `true` once the component is spent and can be removed.
+    fn tick(&mut self, dt: Duration) -> bool;
+}
+
+impl<T: WithTimer> Cooldown for T {
+    fn tick(&mut self, dt: Duration) -> bool {
+        self.timer_mut().tick(dt).finished()
+    }
+}
+
 fn update_cd<T>(
     time: Res<Time>,
     mut commands: Commands,
     mut t_q: Query<(Entity, &mut T)>,
 )
-where T: WithTimer + Component,
+where T: Cooldown + Component,
 {
     let dt = time.delta();
     for (e, mut t) in t_q.iter_mut() {
-        if t.timer_mut().tick(dt).finished() {
+        if t.tick(dt) {
             commands.entity(e).remove::<T>();
         }
     }
 }
 
 
-fn update_ttl(
+pub fn update_ttl(
     timer: Res<Time>,
     mut commands: Commands,
     mut ttl_q: Query<(Entity, &mut TTL)>,